@@ -0,0 +1,57 @@
+//! Times the interpreter on some of the example programs: `cargo bench`.
+//!
+//! Only running the programs is timed: they're tokenized beforehand, and what they write
+//! goes nowhere.
+//!
+//! Before the bytecode VM, the interpreter walked the operations and looked variables up by
+//! name. Timed the same way on the same machine (its `print!`s changed to write to
+//! `io::sink()`), it took this long, next to the VM:
+//!
+//! ```text
+//!                                      operations     bytecode
+//! examples/fibonacci.stk                  62.79µs      17.44µs
+//! examples/multiplication_table.stk       36.36µs      28.96µs
+//! ```
+
+use std::{
+    fs::read_to_string,
+    io::sink,
+    time::{Duration, Instant},
+};
+
+use stuck::Interpreter;
+
+/// The programs run in batches of `RUNS`, and the fastest batch counts, so that other
+/// things happening on the machine disturb the timings less.
+const BATCHES: u32 = 10;
+const RUNS: u32 = 200;
+
+fn bench(path: &str) {
+    let source = read_to_string(path).unwrap_or_else(|err| panic!("can't read {}: {}", path, err));
+
+    let mut fastest = Duration::MAX;
+    for _ in 0..BATCHES {
+        let mut elapsed = Duration::ZERO;
+        for _ in 0..RUNS {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_output(Box::new(sink()));
+            let start = interpreter
+                .load(&source)
+                .unwrap_or_else(|err| panic!("{}", err));
+
+            let started = Instant::now();
+            interpreter
+                .run_loaded(start)
+                .unwrap_or_else(|err| panic!("{}", err));
+            elapsed += started.elapsed();
+        }
+        fastest = fastest.min(elapsed);
+    }
+
+    println!("{:<36} {:>12.2?} per run", path, fastest / RUNS);
+}
+
+fn main() {
+    bench("examples/fibonacci.stk");
+    bench("examples/multiplication_table.stk");
+}
@@ -0,0 +1,436 @@
+use std::{cmp::Ordering, fmt};
+
+/// An arbitrarily large integer, for integer arithmetic that overflows an `i64`.
+///
+/// The magnitude is stored as base 2^32 digits, least significant first, without leading
+/// zeros (so zero has no digits and is never negative).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BigInt {
+    negative: bool,
+    digits: Vec<u32>,
+}
+
+impl BigInt {
+    /// Parses an optionally signed decimal number.
+    pub fn from_decimal(decimal: &str) -> Option<Self> {
+        let (negative, digits) = match decimal.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, decimal.strip_prefix('+').unwrap_or(decimal)),
+        };
+        if digits.is_empty() || !digits.bytes().all(|digit| digit.is_ascii_digit()) {
+            return None;
+        }
+
+        let mut magnitude = Vec::new();
+        for digit in digits.bytes() {
+            multiply_add(&mut magnitude, 10, u32::from(digit - b'0'));
+        }
+        Some(Self::new(negative, magnitude))
+    }
+
+    /// Truncates `number` towards zero, or returns `None` if it's NaN or infinite.
+    pub fn from_f64(number: f64) -> Option<Self> {
+        if !number.is_finite() {
+            return None;
+        }
+        let number = number.trunc();
+        if number.abs() < 2f64.powi(63) {
+            return Some(Self::from(number as i64));
+        }
+
+        // Floats this large are whole numbers: a 53 bit mantissa shifted left.
+        let bits = number.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as usize - 1075;
+        let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+        let mut magnitude = vec![mantissa as u32, (mantissa >> 32) as u32];
+        shift_left(&mut magnitude, exponent);
+        Some(Self::new(number < 0.0, magnitude))
+    }
+
+    /// Returns the value as an `i64`, if it fits.
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.digits.len() > 2 {
+            return None;
+        }
+        let magnitude = self
+            .digits
+            .iter()
+            .rev()
+            .fold(0u64, |value, digit| value << 32 | u64::from(*digit));
+        if self.negative {
+            0i64.checked_sub_unsigned(magnitude)
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+    }
+
+    /// Returns the closest float (or infinity, if it's out of range).
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self.digits.iter().rev().fold(0.0, |value, digit| {
+            value * 4_294_967_296.0 + f64::from(*digit)
+        });
+        if self.negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    /// Whether this is zero.
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// Returns `self + other`.
+    pub fn add(&self, other: &Self) -> Self {
+        if self.negative == other.negative {
+            return Self::new(self.negative, add_magnitudes(&self.digits, &other.digits));
+        }
+        match compare_magnitudes(&self.digits, &other.digits) {
+            Ordering::Less => Self::new(
+                other.negative,
+                subtract_magnitudes(&other.digits, &self.digits),
+            ),
+            _ => Self::new(
+                self.negative,
+                subtract_magnitudes(&self.digits, &other.digits),
+            ),
+        }
+    }
+
+    /// Returns `self - other`.
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    /// Returns `self * other`.
+    pub fn mul(&self, other: &Self) -> Self {
+        let mut product = vec![0u32; self.digits.len() + other.digits.len()];
+        for (i, a) in self.digits.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in other.digits.iter().enumerate() {
+                let digit = u64::from(product[i + j]) + u64::from(*a) * u64::from(*b) + carry;
+                product[i + j] = digit as u32;
+                carry = digit >> 32;
+            }
+            product[i + other.digits.len()] = carry as u32;
+        }
+        Self::new(self.negative != other.negative, product)
+    }
+
+    /// Divides truncating towards zero, like `i64` does: the remainder has the sign of `self`.
+    ///
+    /// Panics if `other` is zero.
+    pub fn div_rem(&self, other: &Self) -> (Self, Self) {
+        assert!(!other.is_zero(), "attempt to divide by zero");
+        let (quotient, remainder) = divide_magnitudes(&self.digits, &other.digits);
+        (
+            Self::new(self.negative != other.negative, quotient),
+            Self::new(self.negative, remainder),
+        )
+    }
+
+    /// Returns `self / other`, truncated towards zero.
+    ///
+    /// Panics if `other` is zero.
+    pub fn div(&self, other: &Self) -> Self {
+        self.div_rem(other).0
+    }
+
+    /// Returns `self % other`, which has the sign of `self`.
+    ///
+    /// Panics if `other` is zero.
+    pub fn rem(&self, other: &Self) -> Self {
+        self.div_rem(other).1
+    }
+
+    /// Returns `-self`.
+    pub fn neg(&self) -> Self {
+        Self::new(!self.negative, self.digits.clone())
+    }
+
+    fn new(negative: bool, mut digits: Vec<u32>) -> Self {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Self {
+            negative: negative && !digits.is_empty(),
+            digits,
+        }
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(integer: i64) -> Self {
+        let magnitude = integer.unsigned_abs();
+        Self::new(
+            integer < 0,
+            vec![magnitude as u32, (magnitude >> 32) as u32],
+        )
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.digits, &other.digits),
+            (true, true) => compare_magnitudes(&other.digits, &self.digits),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // Peels off 9 decimal digits at a time, least significant first.
+        let mut magnitude = self.digits.clone();
+        let mut chunks = Vec::new();
+        while !magnitude.is_empty() {
+            chunks.push(divide_small(&mut magnitude, 1_000_000_000));
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", chunks.pop().unwrap_or(0))?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut sum = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+    for index in 0..a.len().max(b.len()) {
+        let digit =
+            u64::from(*a.get(index).unwrap_or(&0)) + u64::from(*b.get(index).unwrap_or(&0)) + carry;
+        sum.push(digit as u32);
+        carry = digit >> 32;
+    }
+    sum.push(carry as u32);
+    sum
+}
+
+/// `a - b`, where `a` must not be smaller than `b`.
+fn subtract_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (index, digit) in a.iter().enumerate() {
+        let mut digit = i64::from(*digit) - i64::from(*b.get(index).unwrap_or(&0)) - borrow;
+        borrow = 0;
+        if digit < 0 {
+            digit += 1 << 32;
+            borrow = 1;
+        }
+        difference.push(digit as u32);
+    }
+    difference
+}
+
+/// `magnitude = magnitude * factor + addend`.
+fn multiply_add(magnitude: &mut Vec<u32>, factor: u32, addend: u32) {
+    let mut carry = u64::from(addend);
+    for digit in magnitude.iter_mut() {
+        let product = u64::from(*digit) * u64::from(factor) + carry;
+        *digit = product as u32;
+        carry = product >> 32;
+    }
+    if carry > 0 {
+        magnitude.push(carry as u32);
+    }
+}
+
+/// Divides `magnitude` by `divisor` in place and returns the remainder.
+fn divide_small(magnitude: &mut Vec<u32>, divisor: u32) -> u32 {
+    let mut remainder = 0u64;
+    for digit in magnitude.iter_mut().rev() {
+        let dividend = remainder << 32 | u64::from(*digit);
+        *digit = (dividend / u64::from(divisor)) as u32;
+        remainder = dividend % u64::from(divisor);
+    }
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+    remainder as u32
+}
+
+fn shift_left(magnitude: &mut Vec<u32>, bits: usize) {
+    let mut carry = 0u32;
+    if bits % 32 != 0 {
+        for digit in magnitude.iter_mut() {
+            let shifted = u64::from(*digit) << (bits % 32) | u64::from(carry);
+            *digit = shifted as u32;
+            carry = (shifted >> 32) as u32;
+        }
+    }
+    magnitude.push(carry);
+    magnitude.splice(0..0, std::iter::repeat(0).take(bits / 32));
+}
+
+fn divide_magnitudes(dividend: &[u32], divisor: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if let [divisor] = divisor {
+        let mut quotient = dividend.to_vec();
+        let remainder = divide_small(&mut quotient, *divisor);
+        return (quotient, vec![remainder]);
+    }
+
+    // Long division, one bit at a time.
+    let mut quotient = vec![0u32; dividend.len()];
+    let mut remainder: Vec<u32> = Vec::new();
+    for bit in (0..dividend.len() * 32).rev() {
+        shift_left(&mut remainder, 1);
+        if remainder.is_empty() {
+            remainder.push(0);
+        }
+        remainder[0] |= (dividend[bit / 32] >> (bit % 32)) & 1;
+        while remainder.last() == Some(&0) {
+            remainder.pop();
+        }
+        if compare_magnitudes(&remainder, divisor) != Ordering::Less {
+            remainder = subtract_magnitudes(&remainder, divisor);
+            while remainder.last() == Some(&0) {
+                remainder.pop();
+            }
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+    (quotient, remainder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::Object;
+
+    fn big(decimal: &str) -> BigInt {
+        BigInt::from_decimal(decimal).unwrap()
+    }
+
+    /// Numbers around the limb boundaries (2^32 and 2^64) and around `i64`'s range, with
+    /// both signs, which fit in an `i128` to check the results against.
+    fn samples() -> Vec<i128> {
+        let mut samples = vec![0, 1, 7, 10];
+        for boundary in [1i128 << 31, 1 << 32, 1 << 63, 1 << 64] {
+            samples.extend([boundary - 1, boundary, boundary + 1]);
+        }
+        samples.extend([u32::MAX as i128 * 3, 12_345_678_901_234_567_890]);
+        samples.extend(samples.clone().into_iter().map(|sample| -sample));
+        samples
+    }
+
+    #[test]
+    fn arithmetic_agrees_with_i128() {
+        for a in samples() {
+            for b in samples() {
+                let (x, y) = (big(&a.to_string()), big(&b.to_string()));
+                assert_eq!(x.add(&y).to_string(), (a + b).to_string(), "{} + {}", a, b);
+                assert_eq!(x.sub(&y).to_string(), (a - b).to_string(), "{} - {}", a, b);
+                if let Some(product) = a.checked_mul(b) {
+                    assert_eq!(x.mul(&y).to_string(), product.to_string(), "{} * {}", a, b);
+                }
+                if b != 0 {
+                    let (quotient, remainder) = x.div_rem(&y);
+                    assert_eq!(quotient.to_string(), (a / b).to_string(), "{} / {}", a, b);
+                    assert_eq!(remainder.to_string(), (a % b).to_string(), "{} % {}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn carries_and_borrows_cross_limbs() {
+        assert_eq!(big("4294967295").add(&big("1")), big("4294967296"));
+        assert_eq!(
+            big("18446744073709551616").sub(&big("1")),
+            big("18446744073709551615")
+        );
+        assert_eq!(
+            big("18446744073709551615").mul(&big("18446744073709551615")),
+            big("340282366920938463426481119284349108225")
+        );
+        assert_eq!(
+            big("340282366920938463463374607431768211456").div_rem(&big("18446744073709551617")),
+            (big("18446744073709551615"), big("1"))
+        );
+    }
+
+    #[test]
+    fn signs_follow_i64() {
+        assert_eq!(big("-7").div_rem(&big("2")), (big("-3"), big("-1")));
+        assert_eq!(big("7").div_rem(&big("-2")), (big("-3"), big("1")));
+        assert_eq!(big("-7").mul(&big("-2")), big("14"));
+        assert_eq!(big("5").sub(&big("5")), big("0"));
+        // Zero is never negative, however it's made.
+        assert_eq!(big("-0"), big("0"));
+        assert_eq!(big("-5").add(&big("5")), big("0"));
+        assert_eq!(big("0").neg().to_string(), "0");
+        assert!(big("-18446744073709551616") < big("-1"));
+        assert!(big("-1") < big("18446744073709551616"));
+    }
+
+    #[test]
+    fn results_become_integers_again_when_they_fit() {
+        let max = BigInt::from(i64::MAX);
+        let min = BigInt::from(i64::MIN);
+        let one = BigInt::from(1);
+        assert_eq!(
+            Object::from(max.add(&one)),
+            Object::BigInt(big("9223372036854775808"))
+        );
+        assert_eq!(
+            Object::from(min.sub(&one)),
+            Object::BigInt(big("-9223372036854775809"))
+        );
+        assert_eq!(
+            Object::from(max.add(&one).sub(&one)),
+            Object::Integer(i64::MAX)
+        );
+        assert_eq!(Object::from(min.neg().neg()), Object::Integer(i64::MIN));
+        assert_eq!(min.neg().to_i64(), None);
+        assert_eq!(min.to_i64(), Some(i64::MIN));
+    }
+
+    #[test]
+    fn decimals_round_trip() {
+        for decimal in [
+            "0",
+            "-1",
+            "4294967296",
+            "-9223372036854775809",
+            "340282366920938463463374607431768211456",
+        ] {
+            assert_eq!(big(decimal).to_string(), decimal);
+        }
+        assert_eq!(big("+000123").to_string(), "123");
+        for bad in ["", "-", "+", "1.5", "1e5", "12a", "--1", " 1"] {
+            assert_eq!(BigInt::from_decimal(bad), None, "`{}`", bad);
+        }
+    }
+
+    #[test]
+    fn floats_are_truncated() {
+        assert_eq!(BigInt::from_f64(-2.9), Some(BigInt::from(-2)));
+        assert_eq!(BigInt::from_f64(1e20), Some(big("100000000000000000000")));
+        assert_eq!(BigInt::from_f64(f64::NAN), None);
+        assert_eq!(big("100000000000000000000").to_f64(), 1e20);
+    }
+}
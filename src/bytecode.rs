@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+
+use crate::{
+    object::Object,
+    operation::{Operation, OperationType},
+};
+
+/// One operation, encoded for the interpreter's loop.
+///
+/// Every operation gets exactly one instruction, so an instruction's index is also the index
+/// of the operation it came from (which is where diagnostics get their locations from).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    /// Assigns a pending `@` value to the slot, or pushes (or calls) the slot's value.
+    Access(u32),
+    /// `@name`: pops the top of the stack into the slot and skips the identifier after it.
+    Store(u32),
+    /// Pushes a copy of a constant.
+    Constant(u32),
+    True,
+    False,
+
+    /// `[`: jumps to the closing `]`, which turns the body into a function.
+    Define(u32),
+    /// `]`: finishes a function definition or returns from a call.
+    Close,
+    Signature(u32),
+    Global(u32),
+    /// A `@` that isn't directly followed by a name.
+    Assign,
+
+    Plus,
+    Minus,
+    Multiplication,
+    Division,
+    Modulus,
+
+    Equal,
+    Greater,
+    Less,
+
+    Not,
+    And,
+    Or,
+
+    Dup,
+    Drop,
+    Swap,
+    Over,
+    Rot,
+    Nip,
+    Tuck,
+    Pick,
+    Depth,
+
+    Int,
+    Float,
+
+    /// `{`: remembers how deep the stack is.
+    OpenList,
+    /// `}`: collects everything pushed since the matching `{` into a list.
+    CloseList,
+    Len,
+    Get,
+    Set,
+    Push,
+    Pop,
+    Slice,
+    Concat,
+    /// `map`: pushes an empty map.
+    Map,
+    Put,
+    Has,
+    Del,
+    Keys,
+    Values,
+
+    Substr,
+    Index,
+    Split,
+    Join,
+    Upper,
+    Lower,
+    Trim,
+    Replace,
+    StartsWith,
+    EndsWith,
+    Chars,
+    ToStr,
+    ToNum,
+    /// `format`: fills a template's `{}` placeholders with the values below it.
+    Format,
+    /// Pushes the slot's value for a `{name}` placeholder (functions aren't called).
+    Interpolate(u32),
+
+    /// `if` and `while` only mark where blocks start.
+    Nop,
+    /// `then` and `do`: pops a boolean and jumps if it's false.
+    JumpUnless(u32),
+    /// `else` and `end`.
+    Jump(u32),
+    /// `for`: pops a list to loop over, assigning every item to the slot.
+    For(u32),
+    /// `each`: pops a list to loop over, pushing every item.
+    Each,
+    /// The `do` of a `for` or `each`: starts the next iteration or jumps behind the loop.
+    Next(u32),
+
+    Include,
+    /// The end of an included file: jumps back behind its `include`.
+    Return(u32),
+    /// The end of the program.
+    Halt,
+    Read,
+    Write,
+    WriteLine,
+    EWrite,
+    Flush,
+
+    /// An operation without a usable operand, reported when it's reached.
+    Invalid,
+}
+
+/// A signature with its parameters resolved to slots.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameters {
+    pub slots: Vec<usize>,
+    pub results: usize,
+}
+
+/// Gives every variable name a slot, so variables can be indexed instead of hashed.
+#[derive(Debug, Default)]
+pub struct Symbols {
+    slots: HashMap<String, usize>,
+    names: Vec<String>,
+}
+
+impl Symbols {
+    /// Returns the slot of `name`, giving it a new one if it doesn't have one yet.
+    pub(crate) fn slot(&mut self, name: &str) -> usize {
+        if let Some(slot) = self.slots.get(name) {
+            return *slot;
+        }
+        self.names.push(name.to_string());
+        self.slots.insert(name.to_string(), self.names.len() - 1);
+        self.names.len() - 1
+    }
+
+    /// Returns the slot of `name`, if it has one.
+    pub(crate) fn get(&self, name: &str) -> Option<usize> {
+        self.slots.get(name).copied()
+    }
+
+    /// Returns the name a slot was given to.
+    pub(crate) fn name(&self, slot: usize) -> &str {
+        &self.names[slot]
+    }
+
+    /// How many slots were given out (so slots go from 0 up to it).
+    pub(crate) fn len(&self) -> usize {
+        self.names.len()
+    }
+}
+
+/// Instructions together with the constants and signatures they refer to.
+#[derive(Debug, Default)]
+pub struct Bytecode {
+    pub instructions: Vec<Instruction>,
+    pub constants: Vec<Object>,
+    pub parameters: Vec<Parameters>,
+}
+
+impl Bytecode {
+    /// Encodes `operations`, which must continue the program assembled so far.
+    pub fn assemble(&mut self, operations: &[Operation], symbols: &mut Symbols) {
+        // Whether each loop that's still waiting for its `do` is a `while`.
+        let mut loops = Vec::new();
+        for (index, operation) in operations.iter().enumerate() {
+            match operation.op_type {
+                OperationType::While => loops.push(true),
+                OperationType::For | OperationType::Each => loops.push(false),
+                _ => {}
+            }
+            let instruction = match (&operation.op_type, &operation.operand) {
+                (OperationType::Identifier, Some(Object::Identifier(identifier))) => {
+                    Instruction::Access(symbols.slot(identifier) as u32)
+                }
+
+                (
+                    OperationType::Number,
+                    Some(object @ (Object::Integer(_) | Object::BigInt(_) | Object::Number(_))),
+                )
+                | (OperationType::String, Some(object @ Object::String(_))) => {
+                    self.constants.push(object.clone());
+                    Instruction::Constant((self.constants.len() - 1) as u32)
+                }
+
+                (OperationType::True, _) => Instruction::True,
+                (OperationType::False, _) => Instruction::False,
+
+                (OperationType::Function, Some(Object::Reference(ending_block))) => {
+                    Instruction::Define(*ending_block as u32)
+                }
+                (OperationType::Function, None) => Instruction::Close,
+
+                (OperationType::Signature, Some(Object::Signature(signature))) => {
+                    self.parameters.push(Parameters {
+                        slots: signature
+                            .parameters
+                            .iter()
+                            .map(|parameter| symbols.slot(parameter))
+                            .collect(),
+                        results: signature.results,
+                    });
+                    Instruction::Signature((self.parameters.len() - 1) as u32)
+                }
+
+                (OperationType::Global, Some(Object::Identifier(identifier))) => {
+                    Instruction::Global(symbols.slot(identifier) as u32)
+                }
+
+                (OperationType::Assignment, _) => match operations.get(index + 1) {
+                    Some(Operation {
+                        op_type: OperationType::Identifier,
+                        operand: Some(Object::Identifier(identifier)),
+                        ..
+                    }) => Instruction::Store(symbols.slot(identifier) as u32),
+                    _ => Instruction::Assign,
+                },
+
+                (OperationType::Plus, _) => Instruction::Plus,
+                (OperationType::Minus, _) => Instruction::Minus,
+                (OperationType::Multiplication, _) => Instruction::Multiplication,
+                (OperationType::Division, _) => Instruction::Division,
+                (OperationType::Modulus, _) => Instruction::Modulus,
+
+                (OperationType::Equal, _) => Instruction::Equal,
+                (OperationType::Greater, _) => Instruction::Greater,
+                (OperationType::Less, _) => Instruction::Less,
+
+                (OperationType::Not, _) => Instruction::Not,
+                (OperationType::And, _) => Instruction::And,
+                (OperationType::Or, _) => Instruction::Or,
+
+                (OperationType::Dup, _) => Instruction::Dup,
+                (OperationType::Drop, _) => Instruction::Drop,
+                (OperationType::Swap, _) => Instruction::Swap,
+                (OperationType::Over, _) => Instruction::Over,
+                (OperationType::Rot, _) => Instruction::Rot,
+                (OperationType::Nip, _) => Instruction::Nip,
+                (OperationType::Tuck, _) => Instruction::Tuck,
+                (OperationType::Pick, _) => Instruction::Pick,
+                (OperationType::Depth, _) => Instruction::Depth,
+
+                (OperationType::Int, _) => Instruction::Int,
+                (OperationType::Float, _) => Instruction::Float,
+
+                (OperationType::OpenList, _) => Instruction::OpenList,
+                (OperationType::CloseList, _) => Instruction::CloseList,
+                (OperationType::Len, _) => Instruction::Len,
+                (OperationType::Get, _) => Instruction::Get,
+                (OperationType::Set, _) => Instruction::Set,
+                (OperationType::Push, _) => Instruction::Push,
+                (OperationType::Pop, _) => Instruction::Pop,
+                (OperationType::Slice, _) => Instruction::Slice,
+                (OperationType::Concat, _) => Instruction::Concat,
+                (OperationType::Map, _) => Instruction::Map,
+                (OperationType::Put, _) => Instruction::Put,
+                (OperationType::Has, _) => Instruction::Has,
+                (OperationType::Del, _) => Instruction::Del,
+                (OperationType::Keys, _) => Instruction::Keys,
+                (OperationType::Values, _) => Instruction::Values,
+
+                (OperationType::Substr, _) => Instruction::Substr,
+                (OperationType::Index, _) => Instruction::Index,
+                (OperationType::Split, _) => Instruction::Split,
+                (OperationType::Join, _) => Instruction::Join,
+                (OperationType::Upper, _) => Instruction::Upper,
+                (OperationType::Lower, _) => Instruction::Lower,
+                (OperationType::Trim, _) => Instruction::Trim,
+                (OperationType::Replace, _) => Instruction::Replace,
+                (OperationType::StartsWith, _) => Instruction::StartsWith,
+                (OperationType::EndsWith, _) => Instruction::EndsWith,
+                (OperationType::Chars, _) => Instruction::Chars,
+                (OperationType::ToStr, _) => Instruction::ToStr,
+                (OperationType::ToNum, _) => Instruction::ToNum,
+                (OperationType::Format, _) => Instruction::Format,
+                (OperationType::Interpolate, Some(Object::Identifier(identifier))) => {
+                    Instruction::Interpolate(symbols.slot(identifier) as u32)
+                }
+
+                (OperationType::If | OperationType::While, _) => Instruction::Nop,
+                (OperationType::Then, Some(Object::Reference(block))) => {
+                    Instruction::JumpUnless(*block as u32)
+                }
+                (OperationType::Do, Some(Object::Reference(block))) => match loops.pop() {
+                    Some(false) => Instruction::Next(*block as u32),
+                    _ => Instruction::JumpUnless(*block as u32),
+                },
+                (OperationType::For, Some(Object::Identifier(identifier))) => {
+                    Instruction::For(symbols.slot(identifier) as u32)
+                }
+                (OperationType::Each, _) => Instruction::Each,
+                (OperationType::Else | OperationType::End, Some(Object::Reference(block))) => {
+                    Instruction::Jump(*block as u32)
+                }
+
+                (OperationType::Include, _) => Instruction::Include,
+                (OperationType::EndOfFile, Some(Object::Reference(return_to))) => {
+                    Instruction::Return(*return_to as u32)
+                }
+                (OperationType::EndOfFile, _) => Instruction::Halt,
+                (OperationType::Read, _) => Instruction::Read,
+                (OperationType::Write, _) => Instruction::Write,
+                (OperationType::WriteLine, _) => Instruction::WriteLine,
+                (OperationType::EWrite, _) => Instruction::EWrite,
+                (OperationType::Flush, _) => Instruction::Flush,
+
+                _ => Instruction::Invalid,
+            };
+            self.instructions.push(instruction);
+        }
+    }
+}
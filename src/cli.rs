@@ -0,0 +1,120 @@
+//! The command line of the `stuck` binary, which only calls `main`.
+
+use std::{env::args, process::exit};
+
+use crate::{
+    compiler::Compiler, debugger::Console, error::StuckError, interpreter::Interpreter, repl::Repl,
+};
+
+/// Runs `stuck` with the arguments it was started with.
+pub fn main() {
+    let mut args: Vec<String> = args().collect();
+    let max_call_depth = max_call_depth(&mut args);
+
+    match args.len() {
+        1 => {
+            let mut repl = Repl::new(interpreter(max_call_depth));
+            repl.run().unwrap_or_else(|err| {
+                eprintln!("Error: {:#?}", err);
+                exit(1);
+            });
+        }
+
+        2 => {
+            match args[1].as_str() {
+                "help" => {
+                    help(None);
+                }
+
+                source_path => {
+                    let mut interpreter = interpreter(max_call_depth);
+                    if let Err(err) = interpreter.run_file(source_path) {
+                        report(err);
+                    }
+                }
+            };
+        }
+        3 if args[1] == "debug" => {
+            let mut interpreter = interpreter(max_call_depth);
+            interpreter.set_debugger(Some(Box::new(Console::new(&args[2]))));
+            if let Err(err) = interpreter.run_file(&args[2]) {
+                report(err);
+            }
+        }
+        3 => match args[2].as_str() {
+            "-i" => {
+                let mut interpreter = interpreter(max_call_depth);
+                if let Err(err) = interpreter.run_file(&args[1]) {
+                    report(err);
+                }
+            }
+
+            "-c" => {
+                let mut compiler = Compiler::new();
+                if let Some(max_call_depth) = max_call_depth {
+                    compiler.set_max_call_depth(max_call_depth);
+                }
+                if let Err(err) = compiler.compile_file(&args[1]) {
+                    report(err);
+                }
+            }
+
+            invalid_flag => help(Some(&format!("Error: invalid flag `{}`", invalid_flag))),
+        },
+        _ => {
+            help(Some("invalid subcommands"));
+        }
+    }
+}
+
+/// Takes `--max-call-depth <depth>` out of `args`, wherever it is.
+fn max_call_depth(args: &mut Vec<String>) -> Option<usize> {
+    let index = args.iter().position(|arg| arg == "--max-call-depth")?;
+    let depth = match args.get(index + 1).map(|depth| depth.parse()) {
+        Some(Ok(depth)) => depth,
+        _ => help(Some("`--max-call-depth` requires a whole number")),
+    };
+    args.drain(index..index + 2);
+    Some(depth)
+}
+
+fn interpreter(max_call_depth: Option<usize>) -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    if let Some(max_call_depth) = max_call_depth {
+        interpreter.set_max_call_depth(max_call_depth);
+    }
+    interpreter
+}
+
+fn report(error: StuckError) {
+    eprintln!("{}", error);
+    match error.untraced() {
+        StuckError::Io(..) => exit(2),
+        _ => exit(1),
+    }
+}
+
+fn help(message: Option<&str>) -> ! {
+    println!(
+        "\
+program: stuck
+usage:
+commands:
+        stuck               :   runs a stuck repl (type `:help` in it for its commands).
+        stuck [subcommands] [options]
+subcommands:
+        [source_file]       :   interprets the file.
+        [source_file] -i    :   interprets the file.
+        [source_file] -c    :   compiles the file.
+        debug [source_file] :   runs the file in a step debugger (type `help` in it for its commands).
+        help                :   prints this page./
+options:
+        --max-call-depth [depth]    :   limits how deep function calls can nest (default: 10000).
+    "
+    );
+    if let Some(message) = message {
+        eprintln!("Error: {}.", message);
+        exit(1);
+    }
+    exit(0);
+}
@@ -0,0 +1,1230 @@
+use std::{
+    collections::HashMap,
+    env,
+    fs::{read_to_string, write},
+    path::PathBuf,
+    process::Command,
+};
+
+use crate::{
+    error::{Location, StuckError},
+    interpreter::DEFAULT_MAX_CALL_DEPTH,
+    loader::Loader,
+    object::Object,
+    operation::{Operation, OperationType},
+};
+
+const RUNTIME: &str = r#"#include <errno.h>
+#include <limits.h>
+#include <math.h>
+#include <stdarg.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef enum { INTEGER, NUMBER, STRING, BOOLEAN, FUNCTION } Tag;
+
+typedef struct {
+    Tag tag;
+    long long integer;
+    double number;
+    const char *string;
+    int boolean;
+    size_t opening_block;
+} Object;
+
+static Object *stack;
+static size_t stack_len, stack_capacity;
+static Object *registers_ia;
+static size_t registers_ia_len, registers_ia_capacity;
+static size_t *register_f;
+static size_t register_f_len, register_f_capacity;
+
+typedef struct {
+    const char *function;
+    const char *call_site;
+    size_t return_to;
+    size_t base;
+    long results;
+    size_t declared_at;
+    Object *locals;
+    int *defined;
+    int *globals;
+} Frame;
+
+static Object *variables;
+static int *defined;
+static size_t slots;
+static Frame *frames;
+static size_t frames_len, frames_capacity;
+
+typedef struct {
+    size_t depth;
+    size_t return_to;
+} Include;
+
+static Include *includes;
+static size_t includes_len, includes_capacity;
+static int *included;
+
+static void trace(void) {
+    size_t frame = frames_len, repeated;
+    while (frame > 0) {
+        Frame *call = &frames[frame - 1];
+        for (repeated = 1; repeated < frame; repeated++) {
+            Frame *other = &frames[frame - 1 - repeated];
+            if (strcmp(other->function, call->function) || strcmp(other->call_site, call->call_site)) break;
+        }
+        fprintf(stderr, "    in '%s' called at %s", call->function, call->call_site);
+        if (repeated > 1) fprintf(stderr, " (%zu times)", repeated);
+        fputc('\n', stderr);
+        frame -= repeated;
+    }
+}
+
+static void fail(const char *message) {
+    fflush(stdout);
+    fprintf(stderr, "%s\n", message);
+    trace();
+    exit(1);
+}
+
+static void fail_format(const char *format, ...) {
+    va_list arguments;
+    fflush(stdout);
+    va_start(arguments, format);
+    vfprintf(stderr, format, arguments);
+    va_end(arguments);
+    fputc('\n', stderr);
+    trace();
+    exit(1);
+}
+
+static void *grow(void *items, size_t *capacity, size_t size) {
+    *capacity = *capacity ? *capacity * 2 : 256;
+    items = realloc(items, *capacity * size);
+    if (!items) fail("Error: out of memory.");
+    return items;
+}
+
+static void push(Object object) {
+    if (stack_len == stack_capacity) stack = grow(stack, &stack_capacity, sizeof(Object));
+    stack[stack_len++] = object;
+}
+
+static Object pop(void) { return stack[--stack_len]; }
+
+static void push_integer(long long integer) {
+    Object object = {0};
+    object.tag = INTEGER;
+    object.integer = integer;
+    push(object);
+}
+
+static void push_number(double number) {
+    Object object = {0};
+    object.tag = NUMBER;
+    object.number = number;
+    push(object);
+}
+
+static void push_string(const char *string) {
+    Object object = {0};
+    object.tag = STRING;
+    object.string = string;
+    push(object);
+}
+
+static void push_joined(const char *a, const char *b) {
+    char *string = malloc(strlen(a) + strlen(b) + 1);
+    if (!string) fail("Error: out of memory.");
+    strcpy(string, a);
+    strcat(string, b);
+    push_string(string);
+}
+
+static void push_boolean(int boolean) {
+    Object object = {0};
+    object.tag = BOOLEAN;
+    object.boolean = boolean;
+    push(object);
+}
+
+static void push_function(size_t opening_block) {
+    Object object = {0};
+    object.tag = FUNCTION;
+    object.opening_block = opening_block;
+    push(object);
+}
+
+static void push_register_ia(Object object) {
+    if (registers_ia_len == registers_ia_capacity)
+        registers_ia = grow(registers_ia, &registers_ia_capacity, sizeof(Object));
+    registers_ia[registers_ia_len++] = object;
+}
+
+static void push_register_f(size_t opening_block) {
+    if (register_f_len == register_f_capacity)
+        register_f = grow(register_f, &register_f_capacity, sizeof(size_t));
+    register_f[register_f_len++] = opening_block;
+}
+
+static void push_frame(const char *function, const char *call_site, size_t return_to) {
+    Frame *frame;
+    if (frames_len == frames_capacity) frames = grow(frames, &frames_capacity, sizeof(Frame));
+    frame = &frames[frames_len++];
+    frame->function = function;
+    frame->call_site = call_site;
+    frame->return_to = return_to;
+    frame->base = stack_len;
+    frame->results = -1;
+    frame->declared_at = 0;
+    frame->locals = calloc(slots, sizeof(Object));
+    frame->defined = calloc(slots, sizeof(int));
+    frame->globals = calloc(slots, sizeof(int));
+    if (!frame->locals || !frame->defined || !frame->globals) fail("Error: out of memory.");
+}
+
+static void pop_frame(void) {
+    frames_len--;
+    free(frames[frames_len].locals);
+    free(frames[frames_len].defined);
+    free(frames[frames_len].globals);
+}
+
+static void push_include(size_t return_to) {
+    if (includes_len == includes_capacity)
+        includes = grow(includes, &includes_capacity, sizeof(Include));
+    includes[includes_len].depth = frames_len;
+    includes[includes_len++].return_to = return_to;
+}
+
+static int in_call(void) {
+    return frames_len > (includes_len ? includes[includes_len - 1].depth : 0);
+}
+
+static Object *lookup(size_t slot) {
+    if (in_call() && frames[frames_len - 1].defined[slot]) return &frames[frames_len - 1].locals[slot];
+    return defined[slot] ? &variables[slot] : NULL;
+}
+
+static void assign(size_t slot, Object object) {
+    if (in_call() && !frames[frames_len - 1].globals[slot]) {
+        frames[frames_len - 1].locals[slot] = object;
+        frames[frames_len - 1].defined[slot] = 1;
+    } else {
+        variables[slot] = object;
+        defined[slot] = 1;
+    }
+}
+
+static int is_number(Object object) { return object.tag == INTEGER || object.tag == NUMBER; }
+
+static double as_number(Object object) {
+    return object.tag == INTEGER ? (double)object.integer : object.number;
+}
+
+static int equal(Object a, Object b) {
+    if (is_number(a) && is_number(b) && a.tag != b.tag) return as_number(a) == as_number(b);
+    if (a.tag != b.tag) return 0;
+    switch (a.tag) {
+    case INTEGER: return a.integer == b.integer;
+    case NUMBER: return a.number == b.number;
+    case STRING: return strcmp(a.string, b.string) == 0;
+    case BOOLEAN: return a.boolean == b.boolean;
+    case FUNCTION: return a.opening_block == b.opening_block;
+    }
+    return 0;
+}
+
+static void write_number(FILE *out, double number) {
+    char buffer[64], digits[32];
+    int precision, exponent, length = 0;
+    char *cursor;
+
+    if (isnan(number)) { fputs("NaN", out); return; }
+    if (isinf(number)) { fputs(number < 0 ? "-inf" : "inf", out); return; }
+
+    for (precision = 1; precision < 17; precision++) {
+        snprintf(buffer, sizeof buffer, "%.*e", precision - 1, number);
+        if (strtod(buffer, NULL) == number) break;
+    }
+    snprintf(buffer, sizeof buffer, "%.*e", precision - 1, number);
+
+    cursor = buffer;
+    if (*cursor == '-') { fputc('-', out); cursor++; }
+    for (; *cursor != 'e'; cursor++)
+        if (*cursor != '.') digits[length++] = *cursor;
+    digits[length] = '\0';
+    exponent = atoi(cursor + 1);
+
+    if (exponent >= length - 1) {
+        fputs(digits, out);
+        for (int zeros = exponent - (length - 1); zeros > 0; zeros--) fputc('0', out);
+    } else if (exponent >= 0) {
+        fwrite(digits, 1, exponent + 1, out);
+        fprintf(out, ".%s", digits + exponent + 1);
+    } else {
+        fputs("0.", out);
+        for (int zeros = -exponent - 1; zeros > 0; zeros--) fputc('0', out);
+        fputs(digits, out);
+    }
+}
+
+static void write_object(FILE *out, Object object) {
+    switch (object.tag) {
+    case INTEGER: fprintf(out, "%lld", object.integer); break;
+    case NUMBER: write_number(out, object.number); break;
+    case STRING: fputs(object.string, out); break;
+    case BOOLEAN: fputs(object.boolean ? "true" : "false", out); break;
+    case FUNCTION: break;
+    }
+}
+
+static void read_object(void) {
+    size_t length = 0, capacity = 64;
+    char *line = malloc(capacity), *start, *end;
+    int character;
+
+    fflush(stdout);
+    if (!line) fail("Error: out of memory.");
+    while ((character = getchar()) != EOF && character != '\n') {
+        if (length + 1 == capacity) {
+            line = realloc(line, capacity *= 2);
+            if (!line) fail("Error: out of memory.");
+        }
+        line[length++] = (char)character;
+    }
+    line[length] = '\0';
+
+    for (start = line; *start == ' ' || (*start >= '\t' && *start <= '\r'); start++);
+    for (end = start + strlen(start); end > start && (end[-1] == ' ' || (end[-1] >= '\t' && end[-1] <= '\r')); end--);
+    *end = '\0';
+
+    if (*start && strpbrk(start, "xX") == NULL) {
+        char *parsed;
+        long long integer;
+        double number;
+
+        errno = 0;
+        integer = strtoll(start, &parsed, 10);
+        if (*parsed == '\0' && errno != ERANGE) {
+            push_integer(integer);
+            return;
+        }
+        number = strtod(start, &parsed);
+        if (*parsed == '\0' && isfinite(number)) {
+            push_number(number);
+            return;
+        }
+    }
+    push_string(start);
+}
+"#;
+
+/// Compiles stuck programs to native executables by generating C and running `$CC` (or `cc`).
+pub struct Compiler {
+    program: Vec<Operation>,
+    loader: Loader,
+    /// Where the code of every included file starts, by its canonical path. A file is only
+    /// compiled once, and the program keeps track of which files ran.
+    files: HashMap<PathBuf, usize>,
+    /// The file every `include` refers to, as an index into `files`, by the include's index.
+    includes: HashMap<usize, usize>,
+    /// Where every included file starts, by its index.
+    regions: Vec<usize>,
+    variables: HashMap<String, usize>,
+    max_call_depth: usize,
+    output: String,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self {
+            program: Vec::new(),
+            loader: Loader::new(),
+            files: HashMap::new(),
+            includes: HashMap::new(),
+            regions: Vec::new(),
+            variables: HashMap::new(),
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            output: String::new(),
+        }
+    }
+
+    /// Limits how deep (recursive) calls can nest in the executable before it fails.
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    /// Compiles the file at `source_path` to an executable next to it, keeping the generated C.
+    pub fn compile_file(&mut self, source_path: &str) -> Result<(), StuckError> {
+        let path = PathBuf::from(source_path);
+        let mut source = read_to_string(&path)
+            .map_err(|err| StuckError::Io(Location::new(source_path, 0, 0), err.to_string()))?;
+        source.push('\n');
+        let operations = self.loader.load(path.clone(), &source, 0)?;
+        self.load(operations)?;
+
+        self.generate()?;
+
+        let executable_path = path.with_extension("");
+        let c_path = path.with_extension("c");
+        let c_file = c_path.display().to_string();
+        println!("[INFO] generating `{}`", c_file);
+        write(&c_path, &self.output)
+            .map_err(|err| StuckError::Io(Location::new(&c_file, 0, 0), err.to_string()))?;
+
+        let c_compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+        let mut command = Command::new(&c_compiler);
+        command
+            .arg("-O2")
+            .arg("-o")
+            .arg(&executable_path)
+            .arg(&c_path)
+            .arg("-lm");
+        println!(
+            "[CMD] '{} -O2 -o {} {} -lm'",
+            c_compiler,
+            executable_path.display(),
+            c_file
+        );
+        let status = command.status().map_err(|err| {
+            StuckError::Io(
+                Location::new(&c_file, 0, 0),
+                format!("can not run `{}` ({})", c_compiler, err),
+            )
+        })?;
+        if !status.success() {
+            return Err(StuckError::Io(
+                Location::new(&c_file, 0, 0),
+                format!("`{}` failed with {}", c_compiler, status),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Compiler {
+    /// Adds `operations` to the program, along with the files they include.
+    fn load(&mut self, operations: Vec<Operation>) -> Result<(), StuckError> {
+        let offset = self.program.len();
+        self.program.extend(operations);
+        // Included files are added behind `end` and load their own includes.
+        let end = self.program.len();
+        for operation_index in offset..end {
+            if let OperationType::Include = self.program[operation_index].op_type {
+                self.include(operation_index)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Includes are resolved while compiling, so their paths have to be string literals.
+    fn include(&mut self, operation_index: usize) -> Result<(), StuckError> {
+        let operation = &self.program[operation_index];
+        let path = match operation_index
+            .checked_sub(1)
+            .map(|previous| &self.program[previous])
+        {
+            Some(Operation {
+                op_type: OperationType::String,
+                operand: Some(Object::String(path)),
+                ..
+            }) => path.clone(),
+
+            _ => {
+                return Err(self.invalid_include(
+                    operation,
+                    "'include' requires a string literal to be compiled",
+                ))
+            }
+        };
+
+        let span = operation.span;
+        let canonical_path = self.loader.resolve(&path, span)?;
+        // Compiled files return to wherever they were included from, so the `EndOfFile`'s
+        // operand only marks them as included.
+        let region = self.program.len();
+        match self
+            .loader
+            .include(&path, span, region, operation_index + 1)?
+        {
+            Some(operations) => {
+                let index = self.regions.len();
+                self.files.insert(canonical_path, index);
+                self.regions.push(region);
+                self.includes.insert(operation_index, index);
+                self.load(operations)?;
+                self.loader.finish();
+            }
+            None => {
+                if let Some(index) = self.files.get(&canonical_path) {
+                    self.includes.insert(operation_index, *index);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn generate(&mut self) -> Result<(), StuckError> {
+        for operation in &self.program {
+            let names = match &operation.operand {
+                Some(Object::Identifier(identifier)) => vec![identifier],
+                Some(Object::Signature(signature)) => signature.parameters.iter().collect(),
+                _ => Vec::new(),
+            };
+            for name in names {
+                let slot = self.variables.len();
+                self.variables.entry(name.clone()).or_insert(slot);
+            }
+        }
+
+        self.output.push_str(RUNTIME);
+        self.output.push_str(&format!(
+            "
+int main(void) {{
+    size_t target = 0;
+    long long integer;
+    Object a, b, c, *variable;
+    Frame *frame;
+
+    slots = {slots};
+    variables = calloc(slots, sizeof(Object));
+    defined = calloc(slots, sizeof(int));
+    included = calloc({files}, sizeof(int));
+    if (!variables || !defined || !included) fail(\"Error: out of memory.\");
+
+",
+            slots = self.variables.len().max(1),
+            files = self.regions.len().max(1)
+        ));
+
+        let mut jump_targets = Vec::new();
+        for operation_index in 0..self.program.len() {
+            self.output.push_str(&format!("op_{}:\n", operation_index));
+            let code = self.generate_operation(operation_index, &mut jump_targets)?;
+            self.output.push_str(&code);
+        }
+
+        self.output
+            .push_str("    return 0;\n\njump:\n    switch (target) {\n");
+        jump_targets.sort_unstable();
+        jump_targets.dedup();
+        for target in jump_targets {
+            self.output
+                .push_str(&format!("    case {}: goto op_{};\n", target, target));
+        }
+        self.output.push_str("    }\n    return 0;\n}\n");
+
+        Ok(())
+    }
+
+    fn generate_operation(
+        &self,
+        operation_index: usize,
+        jump_targets: &mut Vec<usize>,
+    ) -> Result<String, StuckError> {
+        let operation = &self.program[operation_index];
+        let next = operation_index + 1;
+
+        let code = match operation.op_type {
+            OperationType::Identifier => {
+                let identifier = match &operation.operand {
+                    Some(Object::Identifier(identifier)) => identifier,
+                    _ => return Err(self.invalid_reference(operation, "expected identifier")),
+                };
+                let slot = self.variables[identifier];
+                jump_targets.push(next);
+                format!(
+                    "    if (registers_ia_len) {{
+        assign({slot}, registers_ia[--registers_ia_len]);
+    }} else if (!(variable = lookup({slot}))) {{
+        fail({undefined});
+    }} else if (variable->tag == FUNCTION) {{
+        if (frames_len >= {max_call_depth}) fail({overflow});
+        target = variable->opening_block + 1;
+        push_frame({function}, {call_site}, {next});
+        goto jump;
+    }} else {{
+        push(*variable);
+    }}
+",
+                    slot = slot,
+                    next = next,
+                    max_call_depth = self.max_call_depth,
+                    overflow = self.fail(self.stack_overflow(
+                        operation,
+                        &format!(
+                            "calling '{}' exceeds the maximum call depth of {}",
+                            identifier, self.max_call_depth
+                        )
+                    )),
+                    function = c_string(identifier),
+                    call_site = c_string(&self.location(operation).to_string()),
+                    undefined = self.fail(self.undefined_variable(
+                        operation,
+                        &format!("variable '{}' does not exist", identifier)
+                    )),
+                )
+            }
+
+            OperationType::Number => match &operation.operand {
+                Some(Object::Integer(integer)) => format!("    push_integer({}LL);\n", integer),
+                Some(Object::Number(number)) => format!("    push_number({:?});\n", number),
+                Some(Object::BigInt(integer)) => {
+                    return Err(self.integer_overflow(
+                        operation,
+                        &format!(
+                            "`{}` does not fit in an integer (compiled programs don't have big integers)",
+                            integer
+                        ),
+                    ))
+                }
+                operand => {
+                    return Err(self.invalid_type(
+                        operation,
+                        &format!("expected number, found '{:?}'", operand),
+                    ))
+                }
+            },
+
+            OperationType::String => match &operation.operand {
+                Some(Object::String(string)) if string.contains('\0') => {
+                    return Err(self.unsupported(
+                        operation,
+                        "strings with `\\0` in them are not supported by the compiler yet",
+                    ))
+                }
+                Some(Object::String(string)) => {
+                    format!("    push_string({});\n", c_string(string))
+                }
+                operand => {
+                    return Err(self.invalid_type(
+                        operation,
+                        &format!("expected string, found '{:?}'", operand),
+                    ))
+                }
+            },
+
+            OperationType::True => "    push_boolean(1);\n".to_string(),
+
+            OperationType::False => "    push_boolean(0);\n".to_string(),
+
+            OperationType::Function => match &operation.operand {
+                Some(Object::Reference(ending_block)) => {
+                    jump_targets.push(next);
+                    format!(
+                        "    push_register_f({});\n    goto op_{};\n",
+                        operation_index, ending_block
+                    )
+                }
+
+                _ => format!(
+                    "    if (register_f_len) {{
+        push_function(register_f[--register_f_len]);
+        goto op_{next};
+    }}
+    if (!frames_len) fail({invalid});
+    frame = &frames[frames_len - 1];
+    if (frame->results >= 0) {{
+        if (stack_len < frame->base) fail_format({took}, frame->function, frame->declared_at);
+        if (stack_len - frame->base != (size_t)frame->results)
+            fail_format({leave}, frame->function, frame->declared_at, (size_t)frame->results, stack_len - frame->base);
+    }}
+    target = frame->return_to;
+    pop_frame();
+    goto jump;
+",
+                    next = next,
+                    took = self.fail_format(self.arity_mismatch(
+                        operation,
+                        "function '{name}' (declared at line {line}) \
+                        took more values from the stack than it was given"
+                    )),
+                    leave = self.fail_format(self.arity_mismatch(
+                        operation,
+                        "function '{name}' (declared at line {line}) \
+                        should leave {results} result(s) on the stack, found {found}"
+                    )),
+                    invalid = self.fail(self.invalid_reference(
+                        operation,
+                        "could not figure out where the function is being called from"
+                    )),
+                ),
+            },
+
+            OperationType::Signature => match &operation.operand {
+                Some(Object::Signature(signature)) => {
+                    let arguments = signature.parameters.len();
+                    let mut bind = String::new();
+                    for (index, parameter) in signature.parameters.iter().enumerate() {
+                        bind.push_str(&format!(
+                            "    frame->locals[{slot}] = stack[stack_len + {index}];
+    frame->defined[{slot}] = 1;
+",
+                            slot = self.variables[parameter],
+                            index = index,
+                        ));
+                    }
+                    format!(
+                        "    frame = &frames[frames_len - 1];
+    if (frame->base < {arguments}) {{
+        frames_len--;
+        fail_format({underflow}, frame->call_site, frame->function, frame->base);
+    }}
+    stack_len = frame->base -= {arguments};
+{bind}    frame->results = {results};
+    frame->declared_at = {line};
+",
+                        arguments = arguments,
+                        underflow = self.fail_format(StuckError::StackUnderflow(
+                            Location::new("{site}", 0, 0),
+                            format!(
+                                "function '{{name}}' (declared at line {}) \
+                                expects {} argument(s), found {{found}}",
+                                operation.span.line, arguments
+                            ),
+                        )),
+                        bind = bind,
+                        results = signature.results,
+                        line = operation.span.line,
+                    )
+                }
+                _ => {
+                    return Err(self.invalid_reference(operation, "expected signature"))
+                }
+            },
+
+            OperationType::Global => match &operation.operand {
+                Some(Object::Identifier(identifier)) => format!(
+                    "    if (in_call()) {{
+        frames[frames_len - 1].defined[{slot}] = 0;
+        frames[frames_len - 1].globals[{slot}] = 1;
+    }}
+",
+                    slot = self.variables[identifier]
+                ),
+                _ => return Err(self.invalid_reference(operation, "expected identifier")),
+            },
+
+            OperationType::Assignment => format!(
+                "{}    push_register_ia(pop());\n",
+                self.require(operation, 1, "can not declare variable without a value")
+            ),
+
+            OperationType::Plus => format!(
+                "    if (stack_len >= 2 && (stack[stack_len - 1].tag == STRING || stack[stack_len - 2].tag == STRING)) {{
+        a = pop();
+        b = pop();
+        if (a.tag != STRING || b.tag != STRING) fail({mixed});
+        push_joined(b.string, a.string);
+        goto op_{next};
+    }}
+{arithmetic}",
+                mixed = self.fail(self.invalid_type(
+                    operation,
+                    "'+' can only join a string with another string (see 'tostr')"
+                )),
+                next = next,
+                arithmetic = self.arithmetic(
+                    operation,
+                    "+",
+                    "if (__builtin_add_overflow(b.integer, a.integer, &integer)) fail({overflow});
+        push_integer(integer)",
+                    "push_number(as_number(b) + as_number(a))",
+                ),
+            ),
+            OperationType::Minus => self.arithmetic(
+                operation,
+                "-",
+                "if (__builtin_sub_overflow(b.integer, a.integer, &integer)) fail({overflow});
+        push_integer(integer)",
+                "push_number(as_number(b) - as_number(a))",
+            ),
+            OperationType::Multiplication => self.arithmetic(
+                operation,
+                "*",
+                "if (__builtin_mul_overflow(b.integer, a.integer, &integer)) fail({overflow});
+        push_integer(integer)",
+                "push_number(as_number(b) * as_number(a))",
+            ),
+            OperationType::Division => self.arithmetic(
+                operation,
+                "/",
+                "if (a.integer == 0) fail({zero});
+        if (b.integer == LLONG_MIN && a.integer == -1) fail({overflow});
+        push_integer(b.integer / a.integer)",
+                "push_number(as_number(b) / as_number(a))",
+            ),
+            OperationType::Modulus => self.arithmetic(
+                operation,
+                "%",
+                "if (a.integer == 0) fail({zero});
+        if (b.integer == LLONG_MIN && a.integer == -1) fail({overflow});
+        push_integer(b.integer % a.integer)",
+                "push_number(fmod(as_number(b), as_number(a)))",
+            ),
+
+            OperationType::Equal => format!(
+                "{}    a = pop();\n    b = pop();\n    push_boolean(equal(a, b));\n",
+                self.require(operation, 2, "'=' operation requires two operand")
+            ),
+
+            OperationType::Greater => self.arithmetic(
+                operation,
+                ">",
+                "push_boolean(b.integer > a.integer)",
+                "push_boolean(as_number(b) > as_number(a))",
+            ),
+            OperationType::Less => self.arithmetic(
+                operation,
+                "<",
+                "push_boolean(b.integer < a.integer)",
+                "push_boolean(as_number(b) < as_number(a))",
+            ),
+
+            OperationType::Not => format!(
+                "{require}    a = pop();
+    if (a.tag != BOOLEAN) fail({invalid});
+    push_boolean(!a.boolean);
+",
+                require = self.require(operation, 1, "'!' operator requires one operand"),
+                invalid =
+                    self.fail(self.invalid_type(operation, "'!' is only usable with boolean")),
+            ),
+
+            OperationType::And => self.binary(
+                operation,
+                "&",
+                "BOOLEAN",
+                "push_boolean(a.boolean && b.boolean)",
+            ),
+            OperationType::Or => self.binary(
+                operation,
+                "|",
+                "BOOLEAN",
+                "push_boolean(a.boolean || b.boolean)",
+            ),
+
+            OperationType::Dup => format!(
+                "{}    push(stack[stack_len - 1]);\n",
+                self.require(operation, 1, "'dup' operation requires one operand")
+            ),
+            OperationType::Drop => format!(
+                "{}    pop();\n",
+                self.require(operation, 1, "'drop' operation requires one operand")
+            ),
+            OperationType::Swap => format!(
+                "{}    a = pop();\n    b = pop();\n    push(a);\n    push(b);\n",
+                self.require(operation, 2, "'swap' operation requires two operand")
+            ),
+            OperationType::Over => format!(
+                "{}    push(stack[stack_len - 2]);\n",
+                self.require(operation, 2, "'over' operation requires two operand")
+            ),
+            OperationType::Rot => format!(
+                "{require}    a = pop();
+    b = pop();
+    c = pop();
+    push(b);
+    push(a);
+    push(c);
+",
+                require = self.require(operation, 3, "'rot' operation requires three operand")
+            ),
+            OperationType::Nip => format!(
+                "{}    a = pop();\n    pop();\n    push(a);\n",
+                self.require(operation, 2, "'nip' operation requires two operand")
+            ),
+            OperationType::Tuck => format!(
+                "{}    a = pop();\n    b = pop();\n    push(a);\n    push(b);\n    push(a);\n",
+                self.require(operation, 2, "'tuck' operation requires two operand")
+            ),
+            OperationType::Pick => format!(
+                "{require}    a = pop();
+    if (a.tag != INTEGER || a.integer < 0) fail({invalid});
+    if ((size_t)a.integer >= stack_len) fail({underflow});
+    push(stack[stack_len - 1 - (size_t)a.integer]);
+",
+                require = self.require(operation, 1, "'pick' operation requires one operand"),
+                invalid = self.fail(self.invalid_type(
+                    operation,
+                    "'pick' is only usable with a non-negative integer"
+                )),
+                underflow = self.fail(
+                    self.stack_underflow(operation, "'pick' can not reach deeper than the stack")
+                ),
+            ),
+            OperationType::Depth => "    push_integer((long long)stack_len);\n".to_string(),
+
+            OperationType::Int => format!(
+                "{require}    a = pop();
+    if (a.tag == NUMBER) {{
+        if (!(a.number >= -9223372036854775808.0 && a.number < 9223372036854775808.0)) fail({overflow});
+        push_integer((long long)a.number);
+    }} else if (a.tag == INTEGER) {{
+        push(a);
+    }} else {{
+        fail({invalid});
+    }}
+",
+                require = self.require(operation, 1, "'int' operation requires one operand"),
+                overflow = self.fail(self.integer_overflow(
+                    operation,
+                    "'int' can not turn NaN, infinity or out of range floats into integers"
+                )),
+                invalid =
+                    self.fail(self.invalid_type(operation, "'int' is only usable with number")),
+            ),
+            OperationType::Float => format!(
+                "{require}    a = pop();
+    if (!is_number(a)) fail({invalid});
+    push_number(as_number(a));
+",
+                require = self.require(operation, 1, "'float' operation requires one operand"),
+                invalid =
+                    self.fail(self.invalid_type(operation, "'float' is only usable with number")),
+            ),
+
+            OperationType::If | OperationType::While => String::new(),
+
+            OperationType::Then | OperationType::Do => {
+                let keyword = match operation.op_type {
+                    OperationType::Then => "then",
+                    _ => "do",
+                };
+                match &operation.operand {
+                    Some(Object::Reference(end_block)) => format!(
+                        "{require}    a = pop();
+    if (a.tag != BOOLEAN) fail({invalid});
+    if (!a.boolean) goto op_{end_block};
+",
+                        require = self.require(
+                            operation,
+                            1,
+                            &format!("'{}' operation requires one operand", keyword)
+                        ),
+                        invalid = self.fail(self.invalid_type(
+                            operation,
+                            &format!("'{}' can use with only boolean", keyword)
+                        )),
+                        end_block = end_block,
+                    ),
+                    _ => {
+                        return Err(self.invalid_reference(
+                            operation,
+                            &format!("'{}' does not have reference to it's 'end' block", keyword),
+                        ))
+                    }
+                }
+            }
+
+            OperationType::Else | OperationType::End => match &operation.operand {
+                Some(Object::Reference(block)) => format!("    goto op_{};\n", block),
+                _ => {
+                    return Err(self.invalid_reference(
+                        operation,
+                        &format!(
+                            "'{:?}' does not have reference to it's block",
+                            operation.op_type
+                        ),
+                    ))
+                }
+            },
+
+            OperationType::Include => {
+                let require =
+                    self.require(operation, 1, "'include' operation requires one operand");
+                match self.includes.get(&operation_index) {
+                    // Like the interpreter, every file only runs the first time it's included.
+                    Some(file) => {
+                        jump_targets.push(next);
+                        format!(
+                            "{require}    pop();
+    if (!included[{file}]) {{
+        included[{file}] = 1;
+        push_include({next});
+        goto op_{region};
+    }}
+",
+                            require = require,
+                            file = file,
+                            next = next,
+                            region = self.regions[*file],
+                        )
+                    }
+                    None => format!("{}    pop();\n", require),
+                }
+            }
+
+            OperationType::EndOfFile => match &operation.operand {
+                Some(Object::Reference(_)) => {
+                    "    target = includes[--includes_len].return_to;\n    goto jump;\n".to_string()
+                }
+                _ => "    return 0;\n".to_string(),
+            },
+
+            OperationType::Read => "    read_object();\n".to_string(),
+
+            OperationType::Write | OperationType::WriteLine | OperationType::EWrite => {
+                let (keyword, write) = match operation.op_type {
+                    OperationType::Write => ("write", "write_object(stdout, a)"),
+                    OperationType::WriteLine => (
+                        "writeln",
+                        "write_object(stdout, a);\n    fputc('\\n', stdout)",
+                    ),
+                    _ => ("ewrite", "fflush(stdout);\n    write_object(stderr, a)"),
+                };
+                format!(
+                    "{require}    a = pop();
+    if (a.tag == FUNCTION) fail({invalid});
+    {write};
+",
+                    require = self.require(
+                        operation,
+                        1,
+                        &format!("'{}' operation requires one operand", keyword)
+                    ),
+                    invalid = self.fail(self.invalid_type(
+                        operation,
+                        &format!("'{}' can use with only number, string and boolean", keyword)
+                    )),
+                    write = write,
+                )
+            }
+
+            OperationType::Flush => "    fflush(stdout);\n".to_string(),
+
+            OperationType::OpenList
+            | OperationType::CloseList
+            | OperationType::Len
+            | OperationType::Get
+            | OperationType::Set
+            | OperationType::Push
+            | OperationType::Pop
+            | OperationType::Slice
+            | OperationType::Concat
+            | OperationType::For
+            | OperationType::Each => {
+                return Err(
+                    self.unsupported(operation, "lists are not supported by the compiler yet")
+                )
+            }
+
+            OperationType::Map
+            | OperationType::Put
+            | OperationType::Has
+            | OperationType::Del
+            | OperationType::Keys
+            | OperationType::Values => {
+                return Err(
+                    self.unsupported(operation, "maps are not supported by the compiler yet")
+                )
+            }
+
+            OperationType::Substr
+            | OperationType::Index
+            | OperationType::Split
+            | OperationType::Join
+            | OperationType::Upper
+            | OperationType::Lower
+            | OperationType::Trim
+            | OperationType::Replace
+            | OperationType::StartsWith
+            | OperationType::EndsWith
+            | OperationType::Chars
+            | OperationType::ToStr
+            | OperationType::ToNum
+            | OperationType::Format
+            | OperationType::Interpolate => {
+                return Err(self.unsupported(
+                    operation,
+                    "string words are not supported by the compiler yet",
+                ))
+            }
+        };
+
+        Ok(code)
+    }
+
+    fn require(&self, operation: &Operation, operands: usize, message: &str) -> String {
+        format!(
+            "    if (stack_len < {}) fail({});\n",
+            operands,
+            self.fail(self.stack_underflow(operation, message))
+        )
+    }
+
+    /// Numbers are combined with `integer` if both are integers and with `float` otherwise.
+    /// `integer` can fail with `{overflow}` and `{zero}`.
+    fn arithmetic(
+        &self,
+        operation: &Operation,
+        operator: &str,
+        integer: &str,
+        float: &str,
+    ) -> String {
+        let integer = integer
+            .replace(
+                "{overflow}",
+                &self.fail(self.integer_overflow(
+                    operation,
+                    &format!(
+                        "the result of '{}' does not fit in an integer \
+                        (compiled programs don't have big integers)",
+                        operator
+                    ),
+                )),
+            )
+            .replace(
+                "{zero}",
+                &self.fail(self.division_by_zero(
+                    operation,
+                    &format!("'{}' can not divide an integer by zero", operator),
+                )),
+            );
+        format!(
+            "{require}    a = pop();
+    b = pop();
+    if (a.tag == INTEGER && b.tag == INTEGER) {{
+        {integer};
+    }} else if (is_number(a) && is_number(b)) {{
+        {float};
+    }} else {{
+        fail({invalid});
+    }}
+",
+            require = self.require(
+                operation,
+                2,
+                &format!("'{}' operation requires two operand", operator)
+            ),
+            integer = integer,
+            float = float,
+            invalid = self.fail(self.invalid_type(
+                operation,
+                &format!("'{}' is only usable with number", operator)
+            )),
+        )
+    }
+
+    fn binary(&self, operation: &Operation, operator: &str, tag: &str, statement: &str) -> String {
+        let operand_type = match tag {
+            "BOOLEAN" => "boolean",
+            _ => "number",
+        };
+        format!(
+            "{require}    a = pop();
+    b = pop();
+    if (a.tag != {tag} || b.tag != {tag}) fail({invalid});
+    {statement};
+",
+            require = self.require(
+                operation,
+                2,
+                &format!("'{}' operation requires two operand", operator)
+            ),
+            tag = tag,
+            invalid = self.fail(self.invalid_type(
+                operation,
+                &format!("'{}' is only usable with {}", operator, operand_type)
+            )),
+            statement = statement,
+        )
+    }
+
+    fn fail(&self, error: StuckError) -> String {
+        c_string(&error.to_string())
+    }
+
+    /// Like `fail`, but turns the `{site}`, `{name}`, `{line}`, `{results}` and `{found}`
+    /// placeholders into `printf` conversions that are filled in at runtime.
+    fn fail_format(&self, error: StuckError) -> String {
+        // Only the first line has placeholders, the lines after it are the source it points at.
+        let error = error.to_string();
+        let (message, source) = error.split_at(error.find('\n').unwrap_or(error.len()));
+        c_string(&format!(
+            "{}{}",
+            message
+                .replace('%', "%%")
+                .replace("{site}", "%s")
+                .replace("{name}", "%s")
+                .replace("{line}", "%zu")
+                .replace("{results}", "%zu")
+                .replace("{found}", "%zu"),
+            source.replace('%', "%%")
+        ))
+    }
+}
+
+impl Compiler {
+    fn stack_underflow(&self, operation: &Operation, message: &str) -> StuckError {
+        StuckError::StackUnderflow(self.location(operation), message.to_string())
+    }
+
+    fn undefined_variable(&self, operation: &Operation, message: &str) -> StuckError {
+        StuckError::UndefinedVariable(self.location(operation), message.to_string())
+    }
+
+    fn invalid_reference(&self, operation: &Operation, message: &str) -> StuckError {
+        StuckError::InvalidReference(self.location(operation), message.to_string())
+    }
+
+    fn invalid_type(&self, operation: &Operation, message: &str) -> StuckError {
+        StuckError::InvalidType(self.location(operation), message.to_string())
+    }
+
+    fn invalid_include(&self, operation: &Operation, message: &str) -> StuckError {
+        StuckError::InvalidInclude(self.location(operation), message.to_string())
+    }
+
+    fn stack_overflow(&self, operation: &Operation, message: &str) -> StuckError {
+        StuckError::StackOverflow(self.location(operation), message.to_string())
+    }
+
+    fn integer_overflow(&self, operation: &Operation, message: &str) -> StuckError {
+        StuckError::IntegerOverflow(self.location(operation), message.to_string())
+    }
+
+    fn division_by_zero(&self, operation: &Operation, message: &str) -> StuckError {
+        StuckError::DivisionByZero(self.location(operation), message.to_string())
+    }
+
+    fn arity_mismatch(&self, operation: &Operation, message: &str) -> StuckError {
+        StuckError::ArityMismatch(self.location(operation), message.to_string())
+    }
+
+    fn unsupported(&self, operation: &Operation, message: &str) -> StuckError {
+        StuckError::Unsupported(self.location(operation), message.to_string())
+    }
+
+    fn location(&self, operation: &Operation) -> Location {
+        self.loader.location(operation.span)
+    }
+}
+
+fn c_string(string: &str) -> String {
+    let mut literal = String::from("\"");
+    for byte in string.bytes() {
+        match byte {
+            b'"' => literal.push_str("\\\""),
+            b'\\' => literal.push_str("\\\\"),
+            b'\n' => literal.push_str("\\n"),
+            b'\t' => literal.push_str("\\t"),
+            b' '..=b'~' => literal.push(byte as char),
+            _ => literal.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    literal.push('"');
+    literal
+}
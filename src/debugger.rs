@@ -0,0 +1,334 @@
+use std::{io::ErrorKind, path::Path};
+
+use crate::{
+    editor::Editor,
+    interpreter::{Debugger, Interpreter},
+    object::Object,
+    repl::{describe, show_operation, stack_line},
+};
+
+const PROMPT: &str = "debug :> ";
+
+/// The commands, for completing them.
+const COMMANDS: &[&str] = &[
+    "step", "next", "continue", "break", "delete", "watch", "unwatch", "print", "stack", "frames",
+    "vars", "help", "quit",
+];
+
+const HELP: &str = "\
+step, s                 runs the next operation
+next, n                 runs the next operation, stepping over function calls
+continue, c             runs until a breakpoint or watchpoint is hit
+break, b [file:]<line>  pauses whenever the program gets to a line (without a line, lists them)
+delete, d [file:]<line> removes a breakpoint
+watch, w <name>         pauses whenever a variable changes (without a name, lists them)
+unwatch <name>          removes a watchpoint
+print, p [name]         shows where the program is, or the value of a variable
+stack                   shows the stack
+frames                  shows the function calls in progress
+vars                    shows the locals of the current call and every variable
+help, h                 shows this page
+quit, q                 stops the program
+An empty line repeats the last command. The commands are read from stdin, like the program's
+`read`s: a `read` takes the next line typed, whether the program is paused or not.";
+
+/// A debugger that pauses the program and takes commands from the terminal, for
+/// `stuck debug <file>`. It pauses before the first operation, so breakpoints and
+/// watchpoints can be set before anything runs.
+///
+/// Commands are read from stdin, which the program's `read` reads from too: whatever is typed
+/// while the program runs (rather than at the debugger's prompt) goes to the program.
+pub struct Console {
+    editor: Editor,
+    /// The file a breakpoint without one is in.
+    file: String,
+    breakpoints: Vec<(String, usize)>,
+    /// The variables that are watched, with the values they had when they were last looked at.
+    watchpoints: Vec<(String, Option<Object>)>,
+    mode: Mode,
+    /// The file and line of the last operation at every call depth, so breakpoints only
+    /// pause when a line is entered (not before every operation on it, nor when a call
+    /// returns to it).
+    lines: Vec<(usize, usize)>,
+    last_command: String,
+    /// Whether the hint about `help` was shown yet.
+    greeted: bool,
+}
+
+/// When to pause next, besides breakpoints and watchpoints.
+enum Mode {
+    /// Before the next operation.
+    Step,
+    /// Before the next operation that isn't inside a call deeper than this.
+    Next(usize),
+    /// Only at breakpoints and watchpoints.
+    Continue,
+}
+
+impl Console {
+    /// Creates a debugger for the program in `file` (which is what `break <line>` refers to).
+    pub fn new(file: &str) -> Self {
+        Self {
+            editor: Editor::new(None),
+            file: file.to_string(),
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            mode: Mode::Step,
+            lines: Vec::new(),
+            last_command: String::new(),
+            greeted: false,
+        }
+    }
+}
+
+impl Debugger for Console {
+    fn before(&mut self, interpreter: &Interpreter, at: usize) -> bool {
+        let span = interpreter.operation(at).span;
+        let depth = interpreter.call_depth();
+        let entered = self.lines.get(depth) != Some(&(span.file, span.line));
+        self.lines.truncate(depth);
+        self.lines.resize(depth, (0, 0));
+        self.lines.push((span.file, span.line));
+
+        let mut reasons = Vec::new();
+        for (name, value) in &mut self.watchpoints {
+            let current = interpreter.visible_variable(name);
+            if current != value.as_ref() {
+                reasons.push(format!(
+                    "`{}` changed from {} to {}",
+                    name,
+                    show_value(value.as_ref()),
+                    show_value(current)
+                ));
+                *value = current.cloned();
+            }
+        }
+        if entered && self.is_breakpoint(interpreter, at) {
+            reasons.push(format!("breakpoint at line {}", span.line));
+        }
+
+        let pause = !reasons.is_empty()
+            || match self.mode {
+                Mode::Step => true,
+                Mode::Next(next) => depth <= next,
+                Mode::Continue => false,
+            };
+        if !pause {
+            return true;
+        }
+
+        if !self.greeted {
+            println!("paused before the first operation (type `help` for the commands)");
+            self.greeted = true;
+        }
+        for reason in reasons {
+            println!("{}", reason);
+        }
+        show_position(interpreter, at);
+        println!("{}", stack_line(interpreter.stack()));
+        self.prompt(interpreter, at)
+    }
+}
+
+impl Console {
+    /// Reads commands until one of them resumes (`true`) or stops (`false`) the program.
+    fn prompt(&mut self, interpreter: &Interpreter, at: usize) -> bool {
+        loop {
+            self.editor.set_completions(completions(interpreter));
+            let line = match self.editor.read_line(PROMPT) {
+                Ok(Some(line)) => line,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                // Nobody is left to tell the program to go on.
+                Ok(None) | Err(_) => return false,
+            };
+            let command = if line.trim().is_empty() {
+                self.last_command.clone()
+            } else {
+                self.editor.add_history(&line);
+                self.last_command = line;
+                self.last_command.clone()
+            };
+
+            if let Some(go_on) = self.command(interpreter, at, &command) {
+                return go_on;
+            }
+        }
+    }
+
+    /// Runs a command. Returns whether the program should go on, if the command resumes
+    /// or stops it.
+    fn command(&mut self, interpreter: &Interpreter, at: usize, command: &str) -> Option<bool> {
+        let (name, argument) = match command.trim().split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (command.trim(), ""),
+        };
+
+        match name {
+            "" => {}
+            "step" | "s" => {
+                self.mode = Mode::Step;
+                return Some(true);
+            }
+            "next" | "n" => {
+                self.mode = Mode::Next(interpreter.call_depth());
+                return Some(true);
+            }
+            "continue" | "c" => {
+                self.mode = Mode::Continue;
+                return Some(true);
+            }
+            "break" | "b" if argument.is_empty() => {
+                if self.breakpoints.is_empty() {
+                    println!("no breakpoints");
+                }
+                for (file, line) in &self.breakpoints {
+                    println!("{}:{}", file, line);
+                }
+            }
+            "break" | "b" => match self.breakpoint(argument) {
+                Some(breakpoint) if !self.breakpoints.contains(&breakpoint) => {
+                    self.breakpoints.push(breakpoint)
+                }
+                Some(_) => {}
+                None => eprintln!("`{}` is not a line (like `12` or `lib.stk:12`)", argument),
+            },
+            "delete" | "d" => match self.breakpoint(argument) {
+                Some(breakpoint) if self.breakpoints.contains(&breakpoint) => {
+                    self.breakpoints.retain(|other| *other != breakpoint)
+                }
+                _ => eprintln!("there's no breakpoint at `{}`", argument),
+            },
+            "watch" | "w" if argument.is_empty() => {
+                if self.watchpoints.is_empty() {
+                    println!("no watchpoints");
+                }
+                for (name, value) in &self.watchpoints {
+                    println!("{} = {}", name, show_value(value.as_ref()));
+                }
+            }
+            "watch" | "w" => {
+                if !self.watchpoints.iter().any(|(name, _)| name == argument) {
+                    let value = interpreter.visible_variable(argument).cloned();
+                    self.watchpoints.push((argument.to_string(), value));
+                }
+            }
+            "unwatch" => {
+                if self.watchpoints.iter().any(|(name, _)| name == argument) {
+                    self.watchpoints.retain(|(name, _)| name != argument);
+                } else {
+                    eprintln!("`{}` isn't watched", argument);
+                }
+            }
+            "print" | "p" if argument.is_empty() => show_position(interpreter, at),
+            "print" | "p" => match interpreter.visible_variable(argument) {
+                Some(object) => println!("{} = {}", argument, describe(object)),
+                None => eprintln!("`{}` is not a variable", argument),
+            },
+            "stack" => println!("{}", stack_line(interpreter.stack())),
+            "frames" => {
+                let calls = interpreter.calls();
+                if calls.is_empty() {
+                    println!("no calls in progress");
+                }
+                for (depth, call) in calls.iter().enumerate() {
+                    println!("#{} '{}' called at {}", depth, call.function, call.location);
+                }
+            }
+            "vars" => {
+                let locals = interpreter.locals();
+                if !locals.is_empty() {
+                    println!("locals:");
+                    for (name, object) in locals {
+                        println!("    {} = {}", name, describe(object));
+                    }
+                }
+                let variables = interpreter.variables();
+                if variables.is_empty() {
+                    println!("no variables");
+                } else {
+                    println!("variables:");
+                    for (name, object) in variables {
+                        println!("    {} = {}", name, describe(object));
+                    }
+                }
+            }
+            "help" | "h" => println!("{}", HELP),
+            "quit" | "q" => return Some(false),
+            _ => eprintln!("unknown command `{}` (see `help`)", name),
+        }
+
+        None
+    }
+
+    /// Reads a breakpoint like `12` (in the debugged file) or `lib.stk:12`.
+    fn breakpoint(&self, argument: &str) -> Option<(String, usize)> {
+        match argument.rsplit_once(':') {
+            Some((file, line)) => Some((file.to_string(), line.parse().ok()?)),
+            None => Some((self.file.clone(), argument.parse().ok()?)),
+        }
+    }
+
+    fn is_breakpoint(&self, interpreter: &Interpreter, at: usize) -> bool {
+        let line = interpreter.operation(at).span.line;
+        if !self.breakpoints.iter().any(|(_, other)| *other == line) {
+            return false;
+        }
+        let location = interpreter.location(at);
+        self.breakpoints
+            .iter()
+            .any(|(file, other)| *other == line && is_file(&location.file, file))
+    }
+}
+
+/// Whether a breakpoint's `file` is the one at `path`. Included files are named by their path
+/// from the working directory, but it's enough to give the end of it (in whole components).
+fn is_file(path: &str, file: &str) -> bool {
+    Path::new(path).ends_with(file)
+}
+
+/// Shows the operation that's about to run, and underlines it in its line.
+fn show_position(interpreter: &Interpreter, at: usize) {
+    let location = interpreter.location(at);
+    println!(
+        "{}  {}",
+        location.file,
+        show_operation(interpreter.operation(at))
+    );
+    if let Some(underlined) = location.underlined() {
+        println!("{}", underlined);
+    }
+}
+
+fn show_value(object: Option<&Object>) -> String {
+    object.map_or_else(|| "nothing".to_string(), describe)
+}
+
+/// Commands and the names of the variables (and locals) there are.
+fn completions(interpreter: &Interpreter) -> Vec<String> {
+    let mut completions: Vec<String> = COMMANDS.iter().map(|command| command.to_string()).collect();
+    for (name, _) in interpreter
+        .locals()
+        .into_iter()
+        .chain(interpreter.variables())
+    {
+        completions.push(name.to_string());
+    }
+    completions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_file;
+
+    #[test]
+    fn breakpoints_name_files_by_the_end_of_their_path() {
+        assert!(is_file("main.stk", "main.stk"));
+        assert!(is_file("examples/lib/io.stk", "io.stk"));
+        assert!(is_file("examples/lib/io.stk", "lib/io.stk"));
+        assert!(is_file("./main.stk", "main.stk"));
+
+        assert!(!is_file("examples/lib/io.stk", "o.stk"));
+        assert!(!is_file("examples/lib/io.stk", "b/io.stk"));
+        assert!(!is_file("main.stk", "lib/main.stk"));
+    }
+}
@@ -0,0 +1,540 @@
+use std::{
+    fs::{read_to_string, OpenOptions},
+    io::{self, stdin, stdout, BufRead, IsTerminal, Write},
+    path::PathBuf,
+};
+
+/// How many lines of history are kept.
+const HISTORY_SIZE: usize = 1000;
+
+/// How long to wait (in milliseconds) for the rest of an escape sequence, before taking an
+/// escape as a key press by itself.
+const ESCAPE_TIMEOUT: i32 = 50;
+
+/// Reads lines from the terminal with cursor movement and history, like a (very) small
+/// readline. Without a terminal it falls back to reading plain lines.
+pub struct Editor {
+    history: Vec<String>,
+    history_file: Option<PathBuf>,
+    /// The words Tab completes.
+    completions: Vec<String>,
+    /// Set up on the first line read from a terminal.
+    terminal: Option<Terminal>,
+}
+
+/// The line being edited, with the cursor counted in characters.
+struct Line {
+    characters: Vec<char>,
+    cursor: usize,
+}
+
+/// The terminal's settings, read once per session. It's only in raw mode (no echo, no line
+/// buffering) while a line is edited, so that the program run in between sees it as usual,
+/// and it's restored when this is dropped.
+struct Terminal {
+    settings: sys::Termios,
+    raw: sys::Termios,
+}
+
+impl Editor {
+    /// Creates an editor that loads its history from, and saves it to, `history_file`.
+    pub fn new(history_file: Option<PathBuf>) -> Self {
+        let mut history: Vec<String> = history_file
+            .as_ref()
+            .and_then(|path| read_to_string(path).ok())
+            .map(|history| history.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+        let overflow = history.len().saturating_sub(HISTORY_SIZE);
+        history.drain(..overflow);
+
+        Self {
+            history,
+            history_file,
+            completions: Vec::new(),
+            terminal: None,
+        }
+    }
+
+    /// Reads a line (without its line break), or `None` at the end of the input.
+    ///
+    /// Ctrl-C discards the line and fails with `ErrorKind::Interrupted`.
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        if !stdin().is_terminal() {
+            return self.read_plain_line();
+        }
+        if self.terminal.is_none() {
+            self.terminal = Terminal::new();
+        }
+        match &self.terminal {
+            Some(terminal) => terminal.enable_raw_mode()?,
+            None => {
+                print!("{}", prompt);
+                stdout().flush()?;
+                return self.read_plain_line();
+            }
+        }
+
+        let line = self.edit(prompt);
+        if let Some(terminal) = &self.terminal {
+            terminal.restore();
+        }
+        line
+    }
+
+    /// Sets the words Tab completes.
+    pub fn set_completions(&mut self, completions: Vec<String>) {
+        self.completions = completions;
+    }
+
+    /// Remembers `entry`, so it can be recalled with the arrow keys (now and in later sessions).
+    pub fn add_history(&mut self, entry: &str) {
+        if entry.trim().is_empty() || self.history.last().map(String::as_str) == Some(entry) {
+            return;
+        }
+        self.history.push(entry.to_string());
+        if self.history.len() > HISTORY_SIZE {
+            self.history.remove(0);
+        }
+
+        // History is a convenience, so failing to save it isn't worth interrupting anyone for.
+        if let Some(path) = &self.history_file {
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+                let _ = writeln!(file, "{}", entry);
+            }
+        }
+    }
+}
+
+impl Editor {
+    fn read_plain_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        if stdin().lock().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+
+    fn edit(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        let mut line = Line {
+            characters: Vec::new(),
+            cursor: 0,
+        };
+        // Where in the history the line is from, and what was typed before browsing it.
+        let mut recalled = self.history.len();
+        let mut typed = Vec::new();
+
+        render(prompt, &line)?;
+        loop {
+            let key = match read_key()? {
+                Some(key) => key,
+                None if line.characters.is_empty() => return finish(None),
+                None => break,
+            };
+            match key {
+                Key::Enter => break,
+                Key::Interrupt => {
+                    print!("^C\r\n");
+                    stdout().flush()?;
+                    return Err(io::Error::from(io::ErrorKind::Interrupted));
+                }
+                Key::EndOfInput if line.characters.is_empty() => return finish(None),
+                Key::EndOfInput | Key::Delete => {
+                    if line.cursor < line.characters.len() {
+                        line.characters.remove(line.cursor);
+                    }
+                }
+                Key::Backspace => {
+                    if line.cursor > 0 {
+                        line.cursor -= 1;
+                        line.characters.remove(line.cursor);
+                    }
+                }
+                Key::Left => line.cursor = line.cursor.saturating_sub(1),
+                Key::Right => line.cursor = (line.cursor + 1).min(line.characters.len()),
+                Key::Home => line.cursor = 0,
+                Key::End => line.cursor = line.characters.len(),
+                Key::Up | Key::Down => {
+                    if recalled == self.history.len() {
+                        typed = line.characters.clone();
+                    }
+                    recalled = match key {
+                        Key::Up => recalled.saturating_sub(1),
+                        _ => (recalled + 1).min(self.history.len()),
+                    };
+                    line.characters = match self.history.get(recalled) {
+                        Some(entry) => entry.chars().collect(),
+                        None => typed.clone(),
+                    };
+                    line.cursor = line.characters.len();
+                }
+                Key::KillToEnd => line.characters.truncate(line.cursor),
+                Key::KillToStart => {
+                    line.characters.drain(..line.cursor);
+                    line.cursor = 0;
+                }
+                Key::KillWord => {
+                    let mut start = line.cursor;
+                    while start > 0 && line.characters[start - 1].is_whitespace() {
+                        start -= 1;
+                    }
+                    while start > 0 && !line.characters[start - 1].is_whitespace() {
+                        start -= 1;
+                    }
+                    line.characters.drain(start..line.cursor);
+                    line.cursor = start;
+                }
+                Key::Clear => print!("\x1b[H\x1b[2J"),
+                Key::Tab => self.complete(&mut line),
+                Key::Character(character) => {
+                    line.characters.insert(line.cursor, character);
+                    line.cursor += 1;
+                }
+                Key::Ignored => continue,
+            }
+            render(prompt, &line)?;
+        }
+
+        finish(Some(line.characters.iter().collect()))
+    }
+
+    /// Completes the word before the cursor as far as it's unambiguous. If that's not any
+    /// further than it is already, the words it could become are listed.
+    fn complete(&self, line: &mut Line) {
+        let start = line.characters[..line.cursor]
+            .iter()
+            .rposition(|character| !is_word(*character))
+            .map_or(0, |boundary| boundary + 1);
+        let word: String = line.characters[start..line.cursor].iter().collect();
+        if word.is_empty() {
+            return;
+        }
+        let matches: Vec<&String> = self
+            .completions
+            .iter()
+            .filter(|completion| completion.starts_with(&word))
+            .collect();
+
+        let mut completion = match matches.first() {
+            Some(first) => first.to_string(),
+            None => return,
+        };
+        for other in &matches[1..] {
+            while !other.starts_with(&completion) {
+                completion.pop();
+            }
+        }
+        if matches.len() == 1 {
+            completion.push(' ');
+        }
+
+        if completion.len() > word.len() {
+            for character in completion[word.len()..].chars() {
+                line.characters.insert(line.cursor, character);
+                line.cursor += 1;
+            }
+        } else {
+            let matches: Vec<&str> = matches
+                .iter()
+                .map(|completion| completion.as_str())
+                .collect();
+            print!("\r\n{}\r\n", matches.join("  "));
+        }
+    }
+}
+
+/// Whether `character` can be part of a word that's completed.
+fn is_word(character: char) -> bool {
+    character.is_alphanumeric() || character == '_' || character == ':'
+}
+
+/// Moves to the next line, since the terminal doesn't do it by itself in raw mode.
+fn finish(line: Option<String>) -> io::Result<Option<String>> {
+    print!("\r\n");
+    stdout().flush()?;
+    Ok(line)
+}
+
+fn render(prompt: &str, line: &Line) -> io::Result<()> {
+    let text: String = line.characters.iter().collect();
+    print!("\r{}{}\x1b[K", prompt, text);
+    let behind = line.characters.len() - line.cursor;
+    if behind > 0 {
+        print!("\x1b[{}D", behind);
+    }
+    stdout().flush()
+}
+
+enum Key {
+    Character(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    Up,
+    Down,
+    KillToEnd,
+    KillToStart,
+    KillWord,
+    Clear,
+    Tab,
+    Interrupt,
+    EndOfInput,
+    Ignored,
+}
+
+/// Reads a key press, or `None` when stdin is closed.
+fn read_key() -> io::Result<Option<Key>> {
+    let byte = match read_byte()? {
+        Some(byte) => byte,
+        None => return Ok(None),
+    };
+
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        127 | 8 => Key::Backspace,
+        1 => Key::Home,
+        2 => Key::Left,
+        3 => Key::Interrupt,
+        4 => Key::EndOfInput,
+        5 => Key::End,
+        6 => Key::Right,
+        9 => Key::Tab,
+        11 => Key::KillToEnd,
+        12 => Key::Clear,
+        14 => Key::Down,
+        16 => Key::Up,
+        21 => Key::KillToStart,
+        23 => Key::KillWord,
+        0x1b => read_escape()?,
+        byte if byte < 0x20 => Key::Ignored,
+        byte => read_character(byte)?,
+    };
+
+    Ok(Some(key))
+}
+
+/// Reads what follows an escape: `ESC [ A` for an arrow key, `ESC [ 3 ~` for delete, etc.
+/// An escape that nothing follows (soon enough) is ignored.
+fn read_escape() -> io::Result<Key> {
+    let key = match read_escape_byte()? {
+        Some(b'[' | b'O') => match read_escape_byte()? {
+            Some(b'A') => Key::Up,
+            Some(b'B') => Key::Down,
+            Some(b'C') => Key::Right,
+            Some(b'D') => Key::Left,
+            Some(b'H') => Key::Home,
+            Some(b'F') => Key::End,
+            Some(digit @ b'0'..=b'9') => {
+                let mut code = vec![digit];
+                while let Some(byte) = read_escape_byte()? {
+                    if byte == b'~' || !byte.is_ascii_digit() {
+                        break;
+                    }
+                    code.push(byte);
+                }
+                match code.as_slice() {
+                    b"1" | b"7" => Key::Home,
+                    b"4" | b"8" => Key::End,
+                    b"3" => Key::Delete,
+                    _ => Key::Ignored,
+                }
+            }
+            _ => Key::Ignored,
+        },
+        _ => Key::Ignored,
+    };
+    Ok(key)
+}
+
+/// Reads the rest of a UTF-8 encoded character that starts with `first`.
+fn read_character(first: u8) -> io::Result<Key> {
+    let length = match first {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    };
+    let mut bytes = vec![first];
+    while bytes.len() < length {
+        match read_byte()? {
+            Some(byte) => bytes.push(byte),
+            None => break,
+        }
+    }
+    Ok(match std::str::from_utf8(&bytes) {
+        Ok(character) => character
+            .chars()
+            .next()
+            .map_or(Key::Ignored, Key::Character),
+        Err(_) => Key::Ignored,
+    })
+}
+
+/// Reads a byte straight from stdin: `Stdin` buffers what it reads, which would hide the rest
+/// of an escape sequence from `read_escape_byte`.
+fn read_byte() -> io::Result<Option<u8>> {
+    sys::read_byte()
+}
+
+/// Reads the next byte of an escape sequence, or `None` if it doesn't come in time.
+fn read_escape_byte() -> io::Result<Option<u8>> {
+    match sys::wait_for_input(ESCAPE_TIMEOUT)? {
+        true => read_byte(),
+        false => Ok(None),
+    }
+}
+
+impl Terminal {
+    /// Returns `None` if the terminal can't be configured.
+    fn new() -> Option<Self> {
+        let settings = sys::settings().ok()?;
+        Some(Self {
+            settings,
+            raw: sys::raw(&settings),
+        })
+    }
+
+    fn enable_raw_mode(&self) -> io::Result<()> {
+        sys::set_settings(&self.raw)
+    }
+
+    fn restore(&self) {
+        let _ = sys::set_settings(&self.settings);
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+/// The parts of libc's terminal interface the editor uses, which std doesn't expose.
+#[cfg(unix)]
+mod sys {
+    use std::{
+        io,
+        os::raw::{c_int, c_short},
+    };
+
+    /// A `struct termios`. Its layout differs between systems, but only libc looks inside
+    /// it, so it only has to be big enough for all of them.
+    #[repr(C, align(8))]
+    #[derive(Clone, Copy)]
+    pub struct Termios([u8; 256]);
+
+    #[repr(C)]
+    struct PollFd {
+        fd: c_int,
+        events: c_short,
+        revents: c_short,
+    }
+
+    #[cfg(target_os = "linux")]
+    type PollCount = std::os::raw::c_ulong;
+    #[cfg(not(target_os = "linux"))]
+    type PollCount = std::os::raw::c_uint;
+
+    const STDIN: c_int = 0;
+    const TCSANOW: c_int = 0;
+    const POLLIN: c_short = 1;
+
+    extern "C" {
+        fn tcgetattr(fd: c_int, termios: *mut Termios) -> c_int;
+        fn tcsetattr(fd: c_int, when: c_int, termios: *const Termios) -> c_int;
+        fn cfmakeraw(termios: *mut Termios);
+        fn poll(fds: *mut PollFd, count: PollCount, timeout: c_int) -> c_int;
+        fn read(fd: c_int, buffer: *mut u8, count: usize) -> isize;
+    }
+
+    pub fn settings() -> io::Result<Termios> {
+        let mut termios = Termios([0; 256]);
+        match unsafe { tcgetattr(STDIN, &mut termios) } {
+            0 => Ok(termios),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    pub fn set_settings(termios: &Termios) -> io::Result<()> {
+        match unsafe { tcsetattr(STDIN, TCSANOW, termios) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    pub fn raw(settings: &Termios) -> Termios {
+        let mut termios = *settings;
+        unsafe { cfmakeraw(&mut termios) };
+        termios
+    }
+
+    /// Waits up to `timeout` milliseconds for stdin to have something to read.
+    pub fn wait_for_input(timeout: c_int) -> io::Result<bool> {
+        let mut fd = PollFd {
+            fd: STDIN,
+            events: POLLIN,
+            revents: 0,
+        };
+        loop {
+            match unsafe { poll(&mut fd, 1, timeout) } {
+                -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+                -1 => return Err(io::Error::last_os_error()),
+                ready => return Ok(ready > 0),
+            }
+        }
+    }
+
+    pub fn read_byte() -> io::Result<Option<u8>> {
+        let mut byte = 0;
+        loop {
+            match unsafe { read(STDIN, &mut byte, 1) } {
+                -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+                -1 => return Err(io::Error::last_os_error()),
+                0 => return Ok(None),
+                _ => return Ok(Some(byte)),
+            }
+        }
+    }
+}
+
+/// Without termios there's no raw mode, so the editor reads plain lines.
+#[cfg(not(unix))]
+mod sys {
+    use std::io::{self, stdin, Read};
+
+    #[derive(Clone, Copy)]
+    pub struct Termios;
+
+    pub fn settings() -> io::Result<Termios> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn set_settings(_: &Termios) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub fn raw(settings: &Termios) -> Termios {
+        *settings
+    }
+
+    pub fn wait_for_input(_: i32) -> io::Result<bool> {
+        Ok(true)
+    }
+
+    pub fn read_byte() -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        match stdin().lock().read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+}
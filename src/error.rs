@@ -0,0 +1,235 @@
+use std::{error::Error, fmt};
+
+/// Where an error happened. A `line` of `0` means the error is about the whole file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Location {
+    pub file: String,
+    /// Counted from 1.
+    pub line: usize,
+    /// Counted in characters from 1.
+    pub column: usize,
+    /// The source line, if the error should point into it.
+    pub snippet: Option<Snippet>,
+}
+
+/// A line of source, with how many characters from the location's column the error is about.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Snippet {
+    /// The whole line, without its line break.
+    pub text: String,
+    pub width: usize,
+}
+
+impl Location {
+    pub(crate) fn new(file: &str, line: usize, column: usize) -> Self {
+        Self {
+            file: file.to_string(),
+            line,
+            column,
+            snippet: None,
+        }
+    }
+
+    /// Takes the location's line out of `source`, so the error can underline `width`
+    /// characters of it.
+    pub(crate) fn pointing_into(mut self, source: &str, width: usize) -> Self {
+        if let Some(text) = self
+            .line
+            .checked_sub(1)
+            .and_then(|index| source.lines().nth(index))
+        {
+            self.snippet = Some(Snippet {
+                text: text.to_string(),
+                width: width.max(1),
+            });
+        }
+        self
+    }
+
+    /// The snippet with a caret line under it, like `3 | 1 x +` over `  |   ^`, if there is one.
+    pub fn underlined(&self) -> Option<String> {
+        let snippet = self.snippet.as_ref()?;
+        // Tabs are kept, so the caret lines up however wide they're shown.
+        let indent: String = snippet
+            .text
+            .chars()
+            .take(self.column.saturating_sub(1))
+            .map(|character| if character == '\t' { '\t' } else { ' ' })
+            .collect();
+        let gutter = self.line.to_string();
+        Some(format!(
+            "{} | {}\n{} | {}{}",
+            gutter,
+            snippet.text,
+            " ".repeat(gutter.len()),
+            indent,
+            "^".repeat(snippet.width)
+        ))
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}", self.file)
+        } else {
+            write!(f, "{}:{}:{}", self.file, self.line, self.column)
+        }
+    }
+}
+
+/// A function call that was in progress when an error happened.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Call {
+    /// The name the function was called by.
+    pub function: String,
+    /// Where it was called.
+    pub location: Location,
+}
+
+/// Everything that can go wrong while tokenizing, interpreting or compiling a program.
+#[derive(Debug, PartialEq, Clone)]
+pub enum StuckError {
+    Lexical(Location, String),
+    StackUnderflow(Location, String),
+    UndefinedVariable(Location, String),
+    InvalidReference(Location, String),
+    InvalidType(Location, String),
+    InvalidVariableType(Location, String),
+    InvalidInclude(Location, String),
+    ArityMismatch(Location, String),
+    StackOverflow(Location, String),
+    IntegerOverflow(Location, String),
+    DivisionByZero(Location, String),
+    IndexOutOfRange(Location, String),
+    MissingKey(Location, String),
+    InvalidValue(Location, String),
+    Unsupported(Location, String),
+    Io(Location, String),
+    /// An error raised inside a function, with the calls that led to it (innermost first).
+    Traced(Box<StuckError>, Vec<Call>),
+}
+
+impl StuckError {
+    /// Where the error happened (for a traced error, in the innermost call).
+    pub fn location(&self) -> &Location {
+        match self {
+            StuckError::Lexical(location, _)
+            | StuckError::StackUnderflow(location, _)
+            | StuckError::UndefinedVariable(location, _)
+            | StuckError::InvalidReference(location, _)
+            | StuckError::InvalidType(location, _)
+            | StuckError::InvalidVariableType(location, _)
+            | StuckError::InvalidInclude(location, _)
+            | StuckError::ArityMismatch(location, _)
+            | StuckError::StackOverflow(location, _)
+            | StuckError::IntegerOverflow(location, _)
+            | StuckError::DivisionByZero(location, _)
+            | StuckError::IndexOutOfRange(location, _)
+            | StuckError::MissingKey(location, _)
+            | StuckError::InvalidValue(location, _)
+            | StuckError::Unsupported(location, _)
+            | StuckError::Io(location, _) => location,
+            StuckError::Traced(error, _) => error.location(),
+        }
+    }
+
+    /// What went wrong, without where.
+    pub fn message(&self) -> &str {
+        match self {
+            StuckError::Lexical(_, message)
+            | StuckError::StackUnderflow(_, message)
+            | StuckError::UndefinedVariable(_, message)
+            | StuckError::InvalidReference(_, message)
+            | StuckError::InvalidType(_, message)
+            | StuckError::InvalidVariableType(_, message)
+            | StuckError::InvalidInclude(_, message)
+            | StuckError::ArityMismatch(_, message)
+            | StuckError::StackOverflow(_, message)
+            | StuckError::IntegerOverflow(_, message)
+            | StuckError::DivisionByZero(_, message)
+            | StuckError::IndexOutOfRange(_, message)
+            | StuckError::MissingKey(_, message)
+            | StuckError::InvalidValue(_, message)
+            | StuckError::Unsupported(_, message)
+            | StuckError::Io(_, message) => message,
+            StuckError::Traced(error, _) => error.message(),
+        }
+    }
+
+    /// The kind of error, like `StackUnderflow`, as it's shown to users.
+    pub fn name(&self) -> &str {
+        match self {
+            StuckError::Lexical(..) => "LexicalError",
+            StuckError::StackUnderflow(..) => "StackUnderflow",
+            StuckError::UndefinedVariable(..) => "UndefinedVariable",
+            StuckError::InvalidReference(..) => "InvalidReference",
+            StuckError::InvalidType(..) => "InvalidType",
+            StuckError::InvalidVariableType(..) => "InvalidVariableType",
+            StuckError::InvalidInclude(..) => "InvalidInclude",
+            StuckError::ArityMismatch(..) => "ArityMismatch",
+            StuckError::StackOverflow(..) => "StackOverflow",
+            StuckError::IntegerOverflow(..) => "IntegerOverflow",
+            StuckError::DivisionByZero(..) => "DivisionByZero",
+            StuckError::IndexOutOfRange(..) => "IndexOutOfRange",
+            StuckError::MissingKey(..) => "MissingKey",
+            StuckError::InvalidValue(..) => "InvalidValue",
+            StuckError::Unsupported(..) => "Unsupported",
+            StuckError::Io(..) => "IoError",
+            StuckError::Traced(error, _) => error.name(),
+        }
+    }
+
+    /// The calls that led to the error, innermost first.
+    pub fn trace(&self) -> &[Call] {
+        match self {
+            StuckError::Traced(_, trace) => trace,
+            _ => &[],
+        }
+    }
+
+    /// The error itself, without the calls that led to it.
+    pub fn untraced(&self) -> &StuckError {
+        match self {
+            StuckError::Traced(error, _) => error.untraced(),
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for StuckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}: {}.",
+            self.location(),
+            self.name(),
+            self.message()
+        )?;
+        if let Some(underlined) = self.location().underlined() {
+            write!(f, "\n{}", underlined)?;
+        }
+        // Deep recursion repeats the same call thousands of times, so runs are collapsed.
+        let trace = self.trace();
+        let mut index = 0;
+        while index < trace.len() {
+            let call = &trace[index];
+            let repeated = trace[index..]
+                .iter()
+                .take_while(|other| *other == call)
+                .count();
+            write!(
+                f,
+                "\n    in '{}' called at {}",
+                call.function, call.location
+            )?;
+            if repeated > 1 {
+                write!(f, " ({} times)", repeated)?;
+            }
+            index += repeated;
+        }
+        Ok(())
+    }
+}
+
+impl Error for StuckError {}
@@ -0,0 +1,250 @@
+/// What every builtin word (and symbol) takes from the stack and leaves on it, and what it does.
+const BUILTINS: &[(&str, &str, &str)] = &[
+    ("+", "a b -- sum", "adds two numbers, or joins two strings"),
+    ("-", "a b -- difference", "subtracts b from a"),
+    ("*", "a b -- product", "multiplies two numbers"),
+    (
+        "/",
+        "a b -- quotient",
+        "divides a by b (integers leave out the remainder)",
+    ),
+    ("%", "a b -- remainder", "the remainder of dividing a by b"),
+    ("=", "a b -- boolean", "whether a and b are equal"),
+    (">", "a b -- boolean", "whether a is greater than b"),
+    ("<", "a b -- boolean", "whether a is less than b"),
+    ("!", "a -- boolean", "flips a boolean"),
+    ("&", "a b -- boolean", "whether a and b are both true"),
+    ("|", "a b -- boolean", "whether a or b is true"),
+    (
+        "@",
+        "a --",
+        "`@name` assigns the top of the stack to a variable",
+    ),
+    (
+        "[",
+        "-- function",
+        "`[ a b -- c | ... ]` declares a function",
+    ),
+    ("]", "", "ends a function"),
+    (
+        "{",
+        "-- list",
+        "`{ ... }` collects everything pushed in between into a list",
+    ),
+    ("}", "", "ends a list"),
+    (
+        "\"",
+        "-- string",
+        "`\"...{name}...\"` pushes a string with variables filled in (write `{{` and `}}`, or \
+        use a raw string `r\"...\"`, for braces)",
+    ),
+    ("true", "-- boolean", "pushes true"),
+    ("false", "-- boolean", "pushes false"),
+    (
+        "global",
+        "",
+        "`global name` makes a function assign to the variable outside of it",
+    ),
+    (
+        "include",
+        "path --",
+        "runs a file once, in global scope (paths are relative to the including file)",
+    ),
+    (
+        "read",
+        "-- line",
+        "reads a line from stdin (numbers are read as numbers)",
+    ),
+    ("write", "a --", "writes to stdout"),
+    ("writeln", "a --", "writes to stdout and starts a new line"),
+    ("ewrite", "a --", "writes to stderr"),
+    (
+        "flush",
+        "--",
+        "makes sure everything written so far shows up",
+    ),
+    ("dup", "a -- a a", "copies the top of the stack"),
+    ("drop", "a --", "throws the top of the stack away"),
+    ("swap", "a b -- b a", "exchanges the top two items"),
+    ("over", "a b -- a b a", "copies the second item to the top"),
+    ("rot", "a b c -- b c a", "moves the third item to the top"),
+    ("nip", "a b -- b", "drops the second item"),
+    (
+        "tuck",
+        "a b -- b a b",
+        "copies the top below the second item",
+    ),
+    (
+        "pick",
+        "... n -- ... item",
+        "copies the n-th item below the top (0 pick is dup)",
+    ),
+    ("depth", "-- n", "pushes how many items are on the stack"),
+    (
+        "int",
+        "number -- integer",
+        "drops everything behind the `.`",
+    ),
+    ("float", "number -- float", "turns a number into a float"),
+    (
+        "len",
+        "collection -- n",
+        "how many items (or characters) a list, map or string has",
+    ),
+    (
+        "get",
+        "collection at -- item",
+        "the item at an index of a list, or of a key of a map",
+    ),
+    (
+        "set",
+        "list index item -- list",
+        "replaces the item at an index",
+    ),
+    ("push", "list item -- list", "appends an item"),
+    ("pop", "list -- list item", "takes the last item off"),
+    (
+        "slice",
+        "list start end -- list",
+        "the items from start up to (not including) end",
+    ),
+    ("concat", "a b -- joined", "joins two lists or two strings"),
+    ("map", "-- map", "pushes an empty map"),
+    ("put", "map key value -- map", "stores a value under a key"),
+    (
+        "has",
+        "map key -- boolean",
+        "whether there's a value for a key",
+    ),
+    ("del", "map key -- map", "removes a key"),
+    ("keys", "map -- list", "the keys, in order"),
+    (
+        "values",
+        "map -- list",
+        "the values, in the order of their keys",
+    ),
+    (
+        "format",
+        "values... template -- string",
+        "fills the template's `{}` placeholders",
+    ),
+    (
+        "substr",
+        "string start end -- string",
+        "the characters from start up to (not including) end",
+    ),
+    (
+        "index",
+        "string part -- index",
+        "where part starts (or -1 if it's not there)",
+    ),
+    (
+        "split",
+        "string separator -- list",
+        "cuts a string into a list",
+    ),
+    ("join", "list separator -- string", "glues a list together"),
+    (
+        "upper",
+        "string -- string",
+        "turns a string into upper case",
+    ),
+    (
+        "lower",
+        "string -- string",
+        "turns a string into lower case",
+    ),
+    (
+        "trim",
+        "string -- string",
+        "removes the whitespace around a string",
+    ),
+    (
+        "replace",
+        "string pattern replacement -- string",
+        "replaces every match",
+    ),
+    (
+        "startswith",
+        "string part -- boolean",
+        "whether a string starts with part",
+    ),
+    (
+        "endswith",
+        "string part -- boolean",
+        "whether a string ends with part",
+    ),
+    ("chars", "string -- list", "splits a string into characters"),
+    (
+        "tostr",
+        "a -- string",
+        "turns anything that can be written into a string",
+    ),
+    (
+        "tonum",
+        "string -- number",
+        "reads a number from a string (not `inf` or `nan`)",
+    ),
+    (
+        "if",
+        "",
+        "`if condition then ... else ... end` runs a block if the condition is true",
+    ),
+    ("then", "boolean --", "starts the block of an `if`"),
+    (
+        "else",
+        "",
+        "starts the block that runs if the condition is false",
+    ),
+    (
+        "while",
+        "",
+        "`while condition do ... end` runs a block as long as the condition is true",
+    ),
+    (
+        "for",
+        "list --",
+        "`for name do ... end` runs a block with every item assigned to name",
+    ),
+    (
+        "each",
+        "list --",
+        "`each do ... end` runs a block with every item pushed",
+    ),
+    ("do", "", "starts the block of a loop"),
+    ("end", "", "ends a block"),
+];
+
+/// Returns one line of help for a builtin word, like `dup  ( a -- a a )  copies the top
+/// of the stack`.
+pub fn builtin(word: &str) -> Option<String> {
+    let (_, effect, description) = BUILTINS.iter().find(|(builtin, ..)| *builtin == word)?;
+    if effect.is_empty() {
+        Some(format!("{}  {}", word, description))
+    } else {
+        Some(format!("{}  ( {} )  {}", word, effect, description))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Tokenizer;
+
+    #[test]
+    fn every_keyword_has_help() {
+        for keyword in Tokenizer::keywords() {
+            assert!(builtin(&keyword).is_some(), "`{}` has no help", keyword);
+        }
+    }
+
+    #[test]
+    fn help_shows_the_stack_effect_if_there_is_one() {
+        assert_eq!(
+            builtin("swap").unwrap(),
+            "swap  ( a b -- b a )  exchanges the top two items"
+        );
+        assert_eq!(builtin("do").unwrap(), "do  starts the block of a loop");
+        assert_eq!(builtin("nope"), None);
+    }
+}
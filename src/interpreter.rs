@@ -1,699 +1,1725 @@
-use std::{collections::HashMap, io::stdin, process::exit};
+use std::{
+    collections::BTreeMap,
+    fs::read_to_string,
+    io::{stdin, stdout, Write},
+    path::PathBuf,
+};
 
 use crate::{
-    object::{Boolean, Function, Object},
+    bigint::BigInt,
+    bytecode::{Bytecode, Instruction, Symbols},
+    error::{Call, Location, StuckError},
+    loader::Loader,
+    object::{Boolean, Function, Key, Object},
     operation::{Operation, OperationType},
-    tokenizer::Tokenizer,
+    template::{self, Piece},
 };
 
+/// Runs stuck programs and keeps their stack and variables between runs.
 pub struct Interpreter {
     program: Vec<Operation>,
+    bytecode: Bytecode,
+    symbols: Symbols,
     stack: Vec<Object>,
-    variables: HashMap<String, Object>,
+    variables: Vec<Option<Object>>,
     registers_ia: Vec<Object>,
     register_f: Vec<usize>,
+    loader: Loader,
+    /// How many calls were in progress when each running include started. Included files
+    /// run in global scope, so those calls' locals aren't theirs.
+    include_depths: Vec<usize>,
+    frames: Vec<Frame>,
+    max_call_depth: usize,
+    /// Where `write` and `writeln` go: stdout, unless `set_output` says otherwise.
+    output: Box<dyn Write>,
+    lists: Vec<usize>,
+    iterations: Vec<Iteration>,
+    debugger: Option<Box<dyn Debugger>>,
+}
+
+/// Gets to look at the interpreter before every operation runs, e.g. to pause the program
+/// and let someone inspect it (see `Interpreter::set_debugger`).
+pub trait Debugger {
+    /// Called before the operation at index `at` of the program runs. Returning `false`
+    /// stops the program there.
+    fn before(&mut self, interpreter: &Interpreter, at: usize) -> bool;
+}
+
+/// How many function calls can be in progress at once unless `set_max_call_depth` says otherwise.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 10_000;
+
+/// A function call in progress. Assignments in its body create `locals` unless the name
+/// was declared `global`.
+struct Frame {
+    function: usize,
+    call_site: usize,
+    return_to: usize,
+    base: usize,
+    locals: Vec<(usize, Object)>,
+    globals: Vec<usize>,
+    results: Option<usize>,
+    declared_at: usize,
+}
+
+/// A `for` or `each` loop in progress.
+struct Iteration {
+    /// The items that are still to come, last one first.
+    items: Vec<Object>,
+    /// The slot a `for` assigns every item to (`each` pushes them instead).
+    slot: Option<usize>,
+}
+
+/// The operands of an arithmetic or comparison operator. Mixing an integer with a big
+/// integer turns it into a big integer, mixing any integer with a float turns it into a float.
+enum Numbers {
+    Integers(i64, i64),
+    BigInts(BigInt, BigInt),
+    Floats(f64, f64),
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
+    /// Creates an interpreter with an empty stack and no variables.
     pub fn new() -> Self {
         Self {
             program: Vec::new(),
+            bytecode: Bytecode::default(),
+            symbols: Symbols::default(),
             stack: Vec::new(),
-            variables: HashMap::new(),
+            variables: Vec::new(),
             registers_ia: Vec::new(),
             register_f: Vec::new(),
+            loader: Loader::new(),
+            include_depths: Vec::new(),
+            frames: Vec::new(),
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            output: Box::new(stdout()),
+            lists: Vec::new(),
+            iterations: Vec::new(),
+            debugger: None,
         }
     }
 
-    pub fn run(&mut self, source: &str) {
-        let mut tokenizer = Tokenizer::new(source);
-        self.program = tokenizer.scan_tokens().to_vec();
+    /// Runs `source` on top of the current stack and variables. Functions declared by earlier
+    /// runs can still be called.
+    ///
+    /// If it fails, the stack and the variables are put back the way they were before the run.
+    ///
+    /// Errors are reported as coming from `stdin` and `include`s are resolved
+    /// relative to the working directory.
+    pub fn run(&mut self, source: &str) -> Result<(), StuckError> {
+        self.run_source(source, PathBuf::from("stdin"))
+    }
+
+    /// Tokenizes `source` like `run` does, but doesn't run it yet. Returns where it starts,
+    /// for `run_loaded`. The benchmarks use these to time only the running.
+    #[doc(hidden)]
+    pub fn load(&mut self, source: &str) -> Result<usize, StuckError> {
+        self.load_source(source, PathBuf::from("stdin"))
+    }
+
+    /// Runs what `load` returned the start of.
+    #[doc(hidden)]
+    pub fn run_loaded(&mut self, start: usize) -> Result<(), StuckError> {
+        let stack = self.stack.clone();
+        let variables = self.variables.clone();
+        self.interpret(start).map_err(|error| {
+            let trace = self.calls();
+            self.unwind();
+            self.stack = stack;
+            // Files the run included may have declared variables, which are undeclared again.
+            self.variables = variables;
+            self.variables.resize(self.symbols.len(), None);
+            if trace.is_empty() {
+                error
+            } else {
+                StuckError::Traced(Box::new(error), trace)
+            }
+        })
+    }
+
+    /// Reads and runs the file at `source_path`, resolving its `include`s relative to it.
+    pub fn run_file(&mut self, source_path: &str) -> Result<(), StuckError> {
+        let mut source = read_to_string(source_path)
+            .map_err(|err| StuckError::Io(Location::new(source_path, 0, 0), err.to_string()))?;
+        source.push('\n');
+        self.run_source(&source, PathBuf::from(source_path))
+    }
+
+    /// Pushes `object` on top of the stack, e.g. to pass an argument to the next `run`.
+    pub fn push(&mut self, object: Object) {
+        self.stack.push(object);
+    }
+
+    /// Pops the top of the stack, e.g. to collect what the last `run` left behind.
+    pub fn pop(&mut self) -> Option<Object> {
+        self.stack.pop()
+    }
+
+    /// Returns the value of the variable called `name`, if it's declared.
+    pub fn get_variable(&self, name: &str) -> Option<&Object> {
+        let slot = self.symbols.get(name)?;
+        self.variables.get(slot)?.as_ref()
+    }
+
+    /// Declares the variable `name` (or mutates it, if it's already declared).
+    pub fn set_variable(&mut self, name: &str, object: Object) {
+        let slot = self.symbols.slot(name);
+        self.variables.resize(self.symbols.len(), None);
+        self.variables[slot] = Some(object);
+    }
+
+    /// Returns the stack, bottom first.
+    pub fn stack(&self) -> &[Object] {
+        &self.stack
+    }
+
+    /// Empties the stack, keeping the variables.
+    pub fn clear_stack(&mut self) {
+        self.stack.clear();
+    }
+
+    /// Returns every declared variable with its value, sorted by name.
+    pub fn variables(&self) -> Vec<(&str, &Object)> {
+        let mut variables: Vec<(&str, &Object)> = self
+            .variables
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, object)| Some((self.symbols.name(slot), object.as_ref()?)))
+            .collect();
+        variables.sort_by_key(|(name, _)| *name);
+        variables
+    }
 
-        self.interpret();
+    /// Returns the source `function` was declared with, from its `[` up to its `]`.
+    pub fn function_source(&self, function: &Function) -> Option<&str> {
+        let opening = self.program.get(function.opening_block)?;
+        let closing = match opening.operand {
+            Some(Object::Reference(closing)) => self.program.get(closing)?,
+            _ => return None,
+        };
+        self.loader
+            .source(opening.span.file)
+            .get(opening.span.start..closing.span.end)
+    }
+
+    /// Forgets everything that ran so far (the stack, variables and included files), as if
+    /// the interpreter was new. Settings like the maximum call depth, the debugger and the
+    /// output are kept.
+    pub fn reset(&mut self) {
+        let max_call_depth = self.max_call_depth;
+        let debugger = self.debugger.take();
+        let output = std::mem::replace(&mut self.output, Box::new(stdout()));
+        *self = Self::new();
+        self.max_call_depth = max_call_depth;
+        self.debugger = debugger;
+        self.output = output;
+    }
+
+    /// Installs a debugger that's asked before every operation whether to go on, or removes
+    /// it with `None`.
+    pub fn set_debugger(&mut self, debugger: Option<Box<dyn Debugger>>) {
+        self.debugger = debugger;
+    }
+
+    /// Returns the operation at index `at` of the program (every run and include adds to it).
+    pub(crate) fn operation(&self, at: usize) -> &Operation {
+        &self.program[at]
+    }
+
+    /// Where the operation at index `at` of the program comes from, pointing at its source.
+    pub fn location(&self, at: usize) -> Location {
+        self.loader.location(self.program[at].span)
+    }
+
+    /// Returns how many function calls are in progress.
+    pub fn call_depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns the function calls in progress, innermost first.
+    pub fn calls(&self) -> Vec<Call> {
+        self.frames
+            .iter()
+            .rev()
+            .map(|frame| Call {
+                function: self.symbols.name(frame.function).to_string(),
+                location: self.call_site(frame.call_site),
+            })
+            .collect()
+    }
+
+    /// Returns the locals of the innermost function call with their values, sorted by name.
+    pub fn locals(&self) -> Vec<(&str, &Object)> {
+        let mut locals: Vec<(&str, &Object)> = match self.scope() {
+            Some(frame) => frame
+                .locals
+                .iter()
+                .map(|(slot, object)| (self.symbols.name(*slot), object))
+                .collect(),
+            None => Vec::new(),
+        };
+        locals.sort_by_key(|(name, _)| *name);
+        locals
+    }
+
+    /// Returns what `name` means where the program is: a local of the innermost function
+    /// call, or else the variable.
+    pub fn visible_variable(&self, name: &str) -> Option<&Object> {
+        let slot = self.symbols.get(name)?;
+        match self.scope() {
+            Some(frame) => match frame.locals.iter().find(|(local, _)| *local == slot) {
+                Some((_, object)) => Some(object),
+                None => self.variables.get(slot)?.as_ref(),
+            },
+            None => self.variables.get(slot)?.as_ref(),
+        }
+    }
+
+    /// Limits how deep (recursive) calls can nest before failing with `StackOverflow`.
+    ///
+    /// ```
+    /// use stuck::{Interpreter, StuckError};
+    ///
+    /// let mut interpreter = Interpreter::new();
+    /// interpreter.set_max_call_depth(100);
+    /// let error = interpreter.run("[ n -- | n 1 + forever ] @forever\n0 forever\n").unwrap_err();
+    ///
+    /// assert!(matches!(error.untraced(), StuckError::StackOverflow(..)));
+    /// ```
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    /// Sends what `write` and `writeln` write to `output` instead of stdout (`ewrite` still
+    /// writes to stderr).
+    ///
+    /// ```
+    /// use stuck::Interpreter;
+    ///
+    /// let mut interpreter = Interpreter::new();
+    /// interpreter.set_output(Box::new(std::io::sink()));
+    /// interpreter.run("\"nobody reads this\" writeln\n").unwrap();
+    /// ```
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
     }
 }
 
 impl Interpreter {
-    fn interpret(&mut self) {
-        let mut instruction_pointer = 0;
-
-        while instruction_pointer < self.program.len() {
-            let operation = &self.program[instruction_pointer];
-
-            match operation.op_type {
-                OperationType::Identifier => {
-                    if let Some(variable) = &operation.operand {
-                        match variable {
-                            Object::Identifier(identifier) => {
-                                if let Some(object) = self.registers_ia.pop() {
-                                    self.variables.insert(identifier.to_string(), object);
-                                } else {
-                                    if let Some(object) = self.variables.get(identifier) {
-                                        match object {
-                                            Object::Number(number) => {
-                                                self.stack.push(Object::Number(number.to_owned()))
-                                            }
-
-                                            Object::String(string) => {
-                                                self.stack.push(Object::String(string.clone()))
-                                            }
-
-                                            Object::Boolean(boolean) => {
-                                                self.stack.push(Object::Boolean(boolean.clone()))
-                                            }
-
-                                            Object::Function(function) => {
-                                                self.stack.push(Object::Function(
-                                                    Function::assign_called_from(
-                                                        function.opening_block,
-                                                        instruction_pointer + 1,
-                                                    ),
-                                                ));
-                                                instruction_pointer = function.opening_block;
-                                            }
-
-                                            _ => {
-                                                self.invalid_variable_type(&format!(
-                                                    "in line {}. can use only number, string, boolean or function ('[]')",
-                                                    operation.line
-                                                ));
-                                            }
-                                        }
-                                    } else {
-                                        self.undefined_variable(&format!(
-                                            "variable '{}' does not exist in line {}",
-                                            identifier, operation.line
-                                        ));
-                                    }
-                                }
-                            }
+    fn run_source(&mut self, source: &str, path: PathBuf) -> Result<(), StuckError> {
+        let start = self.load_source(source, path)?;
+        self.run_loaded(start)
+    }
 
-                            _ => {}
-                        }
-                    }
+    fn load_source(&mut self, source: &str, path: PathBuf) -> Result<usize, StuckError> {
+        // Earlier runs stay in the program, so the functions they declared can still be called.
+        let offset = self.program.len();
+        let operations = self.loader.load(path, source, offset)?;
+        self.program.extend(operations);
+        self.include_depths.clear();
+        self.assemble(offset);
+        Ok(offset)
+    }
 
-                    instruction_pointer += 1;
-                }
+    /// Forgets the calls, lists and loops that were in progress when a run ended early.
+    fn unwind(&mut self) {
+        self.registers_ia.clear();
+        self.register_f.clear();
+        self.frames.clear();
+        self.lists.clear();
+        self.iterations.clear();
+    }
 
-                OperationType::String => {
-                    if let Some(operand) = &operation.operand {
-                        match operand {
-                            Object::String(string) => {
-                                self.stack.push(Object::String(string.clone()))
-                            }
+    fn assemble(&mut self, from: usize) {
+        self.bytecode
+            .assemble(&self.program[from..], &mut self.symbols);
+        self.variables.resize(self.symbols.len(), None);
+    }
 
-                            _ => self
-                                .invalid_type(&format!("expected string, found '{:?}'", operation)),
-                        }
-                    }
+    fn include(&mut self, path: &str, at: usize) -> Result<usize, StuckError> {
+        let return_to = at + 1;
+        let offset = self.program.len();
+        let span = self.program[at].span;
+        let operations = match self.loader.include(path, span, offset, return_to)? {
+            Some(operations) => operations,
+            None => return Ok(return_to),
+        };
+        self.program.extend(operations);
+        self.assemble(offset);
+        self.include_depths.push(self.frames.len());
+
+        Ok(offset)
+    }
 
-                    instruction_pointer += 1;
-                }
+    /// The call whose locals are in scope: the innermost one, unless an include started
+    /// after it.
+    fn scope(&self) -> Option<&Frame> {
+        let depth = self.include_depths.last().copied().unwrap_or(0);
+        self.frames.get(depth..)?.last()
+    }
 
-                OperationType::Number => {
-                    if let Some(operand) = &operation.operand {
-                        match operand {
-                            Object::Number(number) => {
-                                self.stack.push(Object::Number(number.to_owned()))
-                            }
+    fn scope_mut(&mut self) -> Option<&mut Frame> {
+        let depth = self.include_depths.last().copied().unwrap_or(0);
+        self.frames.get_mut(depth..)?.last_mut()
+    }
 
-                            _ => self
-                                .invalid_type(&format!("expected number, found '{:?}'", operation)),
-                        }
-                    }
+    fn lookup(&self, slot: usize) -> Option<&Object> {
+        if let Some(frame) = self.scope() {
+            if let Some((_, object)) = frame.locals.iter().find(|(local, _)| *local == slot) {
+                return Some(object);
+            }
+        }
+        self.variables[slot].as_ref()
+    }
 
-                    instruction_pointer += 1;
+    fn assign(&mut self, slot: usize, object: Object) {
+        match self.scope_mut() {
+            Some(frame) if !frame.globals.contains(&slot) => {
+                match frame.locals.iter_mut().find(|(local, _)| *local == slot) {
+                    Some((_, local)) => *local = object,
+                    None => frame.locals.push((slot, object)),
                 }
+            }
+            _ => self.variables[slot] = Some(object),
+        }
+    }
 
-                OperationType::True => {
-                    self.stack.push(Object::Boolean(Boolean::True));
+    fn pop_numbers(&mut self, at: usize, operator: &str) -> Result<Numbers, StuckError> {
+        if self.stack.len() < 2 {
+            return Err(self.stack_underflow(
+                at,
+                &format!("'{}' operation requires two operand", operator),
+            ));
+        }
 
-                    instruction_pointer += 1;
-                }
+        let a = self.stack.pop().unwrap();
+        let b = self.stack.pop().unwrap();
+        match (b, a) {
+            (Object::Integer(x), Object::Integer(y)) => Ok(Numbers::Integers(x, y)),
+            (x, y) if big_integer(&x).is_some() && big_integer(&y).is_some() => Ok(
+                Numbers::BigInts(big_integer(&x).unwrap(), big_integer(&y).unwrap()),
+            ),
+            (x, y) if float(&x).is_some() && float(&y).is_some() => {
+                Ok(Numbers::Floats(float(&x).unwrap(), float(&y).unwrap()))
+            }
+            _ => Err(self.invalid_type(at, &format!("'{}' is only usable with number", operator))),
+        }
+    }
 
-                OperationType::False => {
-                    self.stack.push(Object::Boolean(Boolean::False));
+    fn pop_booleans(&mut self, at: usize, operator: &str) -> Result<(bool, bool), StuckError> {
+        if self.stack.len() < 2 {
+            return Err(self.stack_underflow(
+                at,
+                &format!("'{}' operation requires two operand", operator),
+            ));
+        }
 
-                    instruction_pointer += 1;
-                }
+        let a = self.stack.pop().unwrap();
+        let b = self.stack.pop().unwrap();
+        match (b, a) {
+            (Object::Boolean(x), Object::Boolean(y)) => {
+                Ok((x == Boolean::True, y == Boolean::True))
+            }
+            _ => Err(self.invalid_type(at, &format!("'{}' is only usable with boolean", operator))),
+        }
+    }
 
-                OperationType::Function => {
-                    if let Some(ending_block) = &operation.operand {
-                        match ending_block {
-                            Object::Reference(ending_block) => {
-                                self.register_f.push(instruction_pointer);
-                                instruction_pointer = ending_block.to_owned();
-                            }
+    fn push_boolean(&mut self, boolean: bool) {
+        self.stack.push(Object::Boolean(if boolean {
+            Boolean::True
+        } else {
+            Boolean::False
+        }));
+    }
+
+    /// Checks that `index` is one of the `length` indices of a list.
+    fn index(
+        &self,
+        at: usize,
+        keyword: &str,
+        index: i64,
+        length: usize,
+    ) -> Result<usize, StuckError> {
+        if index < 0 || index as usize >= length {
+            return Err(self.index_out_of_range(
+                at,
+                &format!(
+                    "'{}' can not reach index {} of a list with {} item(s)",
+                    keyword, index, length
+                ),
+            ));
+        }
+        Ok(index as usize)
+    }
+
+    fn pop_string(&mut self, at: usize, keyword: &str) -> Result<String, StuckError> {
+        self.require(
+            at,
+            1,
+            &format!("'{}' operation requires one operand", keyword),
+        )?;
+        match self.stack.pop().unwrap() {
+            Object::String(string) => Ok(string),
+            _ => Err(self.invalid_type(at, &format!("'{}' is only usable with string", keyword))),
+        }
+    }
+
+    fn pop_strings(&mut self, at: usize, keyword: &str) -> Result<(String, String), StuckError> {
+        self.require(
+            at,
+            2,
+            &format!("'{}' operation requires two operand", keyword),
+        )?;
+        let a = self.stack.pop().unwrap();
+        match (self.stack.pop().unwrap(), a) {
+            (Object::String(b), Object::String(a)) => Ok((b, a)),
+            _ => Err(self.invalid_type(
+                at,
+                &format!("'{}' is only usable with two strings", keyword),
+            )),
+        }
+    }
+
+    fn key(&self, at: usize, keyword: &str, object: &Object) -> Result<Key, StuckError> {
+        Key::new(object).ok_or_else(|| {
+            self.invalid_type(
+                at,
+                &format!("'{}' can only use strings and numbers as keys", keyword),
+            )
+        })
+    }
+
+    /// Lends the debugger the interpreter to look at. Returns `false` if it stopped the program.
+    #[cold]
+    fn ask_debugger(&mut self, at: usize) -> bool {
+        let mut debugger = match self.debugger.take() {
+            Some(debugger) => debugger,
+            None => return true,
+        };
+        let go_on = debugger.before(self, at);
+        self.debugger = Some(debugger);
+        go_on
+    }
+
+    fn require(&self, at: usize, operands: usize, message: &str) -> Result<(), StuckError> {
+        if self.stack.len() < operands {
+            return Err(self.stack_underflow(at, message));
+        }
+        Ok(())
+    }
+}
 
-                            _ => {
-                                self.invalid_reference(&format!(
-                                    "invalid reference for 'function' (expected integer) in line {}",
-                                    operation.line
+impl Interpreter {
+    fn interpret(&mut self, start: usize) -> Result<(), StuckError> {
+        let mut instruction_pointer = start;
+
+        while instruction_pointer < self.bytecode.instructions.len() {
+            let at = instruction_pointer;
+            instruction_pointer += 1;
+
+            if self.debugger.is_some() && !self.ask_debugger(at) {
+                self.unwind();
+                return Ok(());
+            }
+
+            match self.bytecode.instructions[at] {
+                Instruction::Access(slot) => {
+                    let slot = slot as usize;
+                    if let Some(object) = self.registers_ia.pop() {
+                        self.assign(slot, object);
+                        continue;
+                    }
+
+                    match self.lookup(slot) {
+                        Some(Object::Function(function)) => {
+                            let opening_block = function.opening_block;
+                            if self.frames.len() >= self.max_call_depth {
+                                return Err(self.stack_overflow(
+                                    at,
+                                    &format!(
+                                        "calling '{}' exceeds the maximum call depth of {}",
+                                        self.symbols.name(slot),
+                                        self.max_call_depth
+                                    ),
                                 ));
                             }
+                            self.frames.push(Frame {
+                                function: slot,
+                                call_site: at,
+                                return_to: at + 1,
+                                base: self.stack.len(),
+                                locals: Vec::new(),
+                                globals: Vec::new(),
+                                results: None,
+                                declared_at: self.program[opening_block].span.line,
+                            });
+                            instruction_pointer = opening_block + 1;
                         }
-                    } else {
-                        if let Some(opening_block) = self.register_f.pop() {
-                            self.stack
-                                .push(Object::Function(Function::new(opening_block)));
-                            instruction_pointer += 1;
-                        } else {
-                            instruction_pointer = match self.stack.pop().unwrap() {
-                                Object::Function(function) => function.called_from,
-                                _ => {
-                                    self.invalid_reference(&format!("could not figure out where the is being called from in line {}", operation.line));
-                                    0
-                                }
-                            }
+
+                        Some(
+                            object @ (Object::Integer(_)
+                            | Object::BigInt(_)
+                            | Object::Number(_)
+                            | Object::String(_)
+                            | Object::Boolean(_)
+                            | Object::List(_)
+                            | Object::Map(_)),
+                        ) => {
+                            let object = object.clone();
+                            self.stack.push(object);
                         }
-                    }
-                }
 
-                OperationType::Assignment => {
-                    if self.stack.len() < 1 {
-                        self.stack_underflow(&format!(
-                            "can not declare variable without a value in line {}",
-                            operation.line
-                        ));
+                        Some(_) => {
+                            return Err(self.invalid_variable_type(
+                                at,
+                                "can use only number, string, boolean, list, map or function ('[]')",
+                            ));
+                        }
+
+                        None => {
+                            return Err(self.undefined_variable(
+                                at,
+                                &format!("variable '{}' does not exist", self.symbols.name(slot)),
+                            ));
+                        }
                     }
+                }
 
+                Instruction::Store(slot) => {
+                    self.require(at, 1, "can not declare variable without a value")?;
                     let object = self.stack.pop().unwrap();
-                    self.registers_ia.push(object);
+                    self.assign(slot as usize, object);
 
                     instruction_pointer += 1;
                 }
 
-                OperationType::Plus => {
-                    if self.stack.len() < 2 {
-                        self.stack_underflow(&format!(
-                            "'+' operation requires two operand in line {}",
-                            operation.line
-                        ));
+                Instruction::Constant(constant) => {
+                    self.stack
+                        .push(self.bytecode.constants[constant as usize].clone());
+                }
+
+                Instruction::True => self.stack.push(Object::Boolean(Boolean::True)),
+
+                Instruction::False => self.stack.push(Object::Boolean(Boolean::False)),
+
+                Instruction::Define(ending_block) => {
+                    self.register_f.push(at);
+                    instruction_pointer = ending_block as usize;
+                }
+
+                Instruction::Close => {
+                    if let Some(opening_block) = self.register_f.pop() {
+                        self.stack
+                            .push(Object::Function(Function::new(opening_block)));
+                        continue;
                     }
 
-                    let a = self.stack.pop().unwrap();
-                    let b = self.stack.pop().unwrap();
-                    match (a, b) {
-                        (Object::Number(x), Object::Number(y)) => {
-                            self.stack.push(Object::Number(x + y));
+                    let frame = match self.frames.last() {
+                        Some(frame) => frame,
+                        None => {
+                            return Err(self.invalid_reference(
+                                at,
+                                "could not figure out where the function is being called from",
+                            ));
                         }
-                        _ => {
-                            self.invalid_type(&format!(
-                                "'+' is only usable with number in line {}",
-                                operation.line
+                    };
+                    if let Some(results) = frame.results {
+                        let function = self.symbols.name(frame.function);
+                        let declared_at = frame.declared_at;
+                        if self.stack.len() < frame.base {
+                            return Err(self.arity_mismatch(
+                                at,
+                                &format!(
+                                    "function '{function}' (declared at line {declared_at}) \
+                                    took more values from the stack than it was given",
+                                ),
+                            ));
+                        }
+                        let found = self.stack.len() - frame.base;
+                        if found != results {
+                            return Err(self.arity_mismatch(
+                                at,
+                                &format!(
+                                    "function '{function}' (declared at line {declared_at}) \
+                                    should leave {results} result(s) on the stack, found {found}",
+                                ),
                             ));
                         }
                     }
+                    instruction_pointer = frame.return_to;
+                    self.frames.pop();
+                }
 
-                    instruction_pointer += 1;
+                Instruction::Signature(parameters) => {
+                    let parameters = &self.bytecode.parameters[parameters as usize];
+                    if let Some(frame) = self.frames.last_mut() {
+                        let arguments = parameters.slots.len();
+                        if frame.base < arguments {
+                            // The call never started, so it doesn't belong in the stack trace.
+                            let frame = self.frames.pop().unwrap();
+                            return Err(self.stack_underflow(
+                                frame.call_site,
+                                &format!(
+                                    "function '{}' (declared at line {}) \
+                                    expects {} argument(s), found {}",
+                                    self.symbols.name(frame.function),
+                                    frame.declared_at,
+                                    arguments,
+                                    frame.base
+                                ),
+                            ));
+                        }
+
+                        let values = self.stack.drain(frame.base - arguments..frame.base);
+                        frame
+                            .locals
+                            .extend(parameters.slots.iter().copied().zip(values));
+                        frame.base -= arguments;
+                        frame.results = Some(parameters.results);
+                    }
                 }
 
-                OperationType::Minus => {
-                    if self.stack.len() < 2 {
-                        self.stack_underflow(&format!(
-                            "'-' operation requires two operand in line {}",
-                            operation.line
-                        ));
+                Instruction::Global(slot) => {
+                    let slot = slot as usize;
+                    if let Some(frame) = self.scope_mut() {
+                        frame.locals.retain(|(local, _)| *local != slot);
+                        frame.globals.push(slot);
                     }
+                }
 
-                    let a = self.stack.pop().unwrap();
-                    let b = self.stack.pop().unwrap();
-                    match (a, b) {
-                        (Object::Number(x), Object::Number(y)) => {
-                            self.stack.push(Object::Number(y - x));
+                Instruction::Assign => {
+                    self.require(at, 1, "can not declare variable without a value")?;
+                    let object = self.stack.pop().unwrap();
+                    self.registers_ia.push(object);
+                }
+
+                Instruction::Plus => {
+                    match self.stack.as_mut_slice() {
+                        [.., Object::String(b), Object::String(a)] => {
+                            let a = std::mem::take(a);
+                            b.push_str(&a);
+                            self.stack.pop();
+                            continue;
                         }
-                        _ => {
-                            self.invalid_type(&format!(
-                                "'-' is only usable with number in line {}",
-                                operation.line
+                        [.., Object::String(_), _] | [.., _, Object::String(_)] => {
+                            return Err(self.invalid_type(
+                                at,
+                                "'+' can only join a string with another string (see 'tostr')",
                             ));
                         }
+                        _ => {}
                     }
+                    let object = match self.pop_numbers(at, "+")? {
+                        Numbers::Integers(x, y) => promote(x, y, i64::checked_add, BigInt::add),
+                        Numbers::BigInts(x, y) => x.add(&y).into(),
+                        Numbers::Floats(x, y) => Object::Number(x + y),
+                    };
+                    self.stack.push(object);
+                }
 
-                    instruction_pointer += 1;
+                Instruction::Minus => {
+                    let object = match self.pop_numbers(at, "-")? {
+                        Numbers::Integers(x, y) => promote(x, y, i64::checked_sub, BigInt::sub),
+                        Numbers::BigInts(x, y) => x.sub(&y).into(),
+                        Numbers::Floats(x, y) => Object::Number(x - y),
+                    };
+                    self.stack.push(object);
                 }
 
-                OperationType::Multiplication => {
-                    if self.stack.len() < 2 {
-                        self.stack_underflow(&format!(
-                            "'*' operation requires two operand in line {}",
-                            operation.line
-                        ));
-                    }
+                Instruction::Multiplication => {
+                    let object = match self.pop_numbers(at, "*")? {
+                        Numbers::Integers(x, y) => promote(x, y, i64::checked_mul, BigInt::mul),
+                        Numbers::BigInts(x, y) => x.mul(&y).into(),
+                        Numbers::Floats(x, y) => Object::Number(x * y),
+                    };
+                    self.stack.push(object);
+                }
 
+                Instruction::Division => {
+                    let object = match self.pop_numbers(at, "/")? {
+                        Numbers::Integers(_, 0) => {
+                            return Err(
+                                self.division_by_zero(at, "'/' can not divide an integer by zero")
+                            );
+                        }
+                        Numbers::BigInts(_, y) if y.is_zero() => {
+                            return Err(
+                                self.division_by_zero(at, "'/' can not divide an integer by zero")
+                            );
+                        }
+                        Numbers::Integers(x, y) => promote(x, y, i64::checked_div, BigInt::div),
+                        Numbers::BigInts(x, y) => x.div(&y).into(),
+                        Numbers::Floats(x, y) => Object::Number(x / y),
+                    };
+                    self.stack.push(object);
+                }
+
+                Instruction::Modulus => {
+                    let object = match self.pop_numbers(at, "%")? {
+                        Numbers::Integers(_, 0) => {
+                            return Err(
+                                self.division_by_zero(at, "'%' can not divide an integer by zero")
+                            );
+                        }
+                        Numbers::BigInts(_, y) if y.is_zero() => {
+                            return Err(
+                                self.division_by_zero(at, "'%' can not divide an integer by zero")
+                            );
+                        }
+                        Numbers::Integers(x, y) => promote(x, y, i64::checked_rem, BigInt::rem),
+                        Numbers::BigInts(x, y) => x.rem(&y).into(),
+                        Numbers::Floats(x, y) => Object::Number(x % y),
+                    };
+                    self.stack.push(object);
+                }
+
+                Instruction::Equal => {
+                    self.require(at, 2, "'=' operation requires two operand")?;
                     let a = self.stack.pop().unwrap();
                     let b = self.stack.pop().unwrap();
-                    match (a, b) {
-                        (Object::Number(x), Object::Number(y)) => {
-                            self.stack.push(Object::Number(x * y));
-                        }
+                    self.push_boolean(equal(&b, &a));
+                }
+
+                Instruction::Greater => {
+                    let boolean = match self.pop_numbers(at, ">")? {
+                        Numbers::Integers(x, y) => x > y,
+                        Numbers::BigInts(x, y) => x > y,
+                        Numbers::Floats(x, y) => x > y,
+                    };
+                    self.push_boolean(boolean);
+                }
+
+                Instruction::Less => {
+                    let boolean = match self.pop_numbers(at, "<")? {
+                        Numbers::Integers(x, y) => x < y,
+                        Numbers::BigInts(x, y) => x < y,
+                        Numbers::Floats(x, y) => x < y,
+                    };
+                    self.push_boolean(boolean);
+                }
+
+                Instruction::Not => {
+                    self.require(at, 1, "'!' operator requires one operand")?;
+                    match self.stack.pop().unwrap() {
+                        Object::Boolean(x) => self.push_boolean(x == Boolean::False),
+                        _ => return Err(self.invalid_type(at, "'!' is only usable with boolean")),
+                    }
+                }
+
+                Instruction::And => {
+                    let (x, y) = self.pop_booleans(at, "&")?;
+                    self.push_boolean(x && y);
+                }
+
+                Instruction::Or => {
+                    let (x, y) = self.pop_booleans(at, "|")?;
+                    self.push_boolean(x || y);
+                }
+
+                Instruction::Dup => {
+                    self.require(at, 1, "'dup' operation requires one operand")?;
+                    let a = self.stack[self.stack.len() - 1].clone();
+                    self.stack.push(a);
+                }
+
+                Instruction::Drop => {
+                    self.require(at, 1, "'drop' operation requires one operand")?;
+                    self.stack.pop();
+                }
+
+                Instruction::Swap => {
+                    self.require(at, 2, "'swap' operation requires two operand")?;
+                    let length = self.stack.len();
+                    self.stack.swap(length - 1, length - 2);
+                }
+
+                Instruction::Over => {
+                    self.require(at, 2, "'over' operation requires two operand")?;
+                    let b = self.stack[self.stack.len() - 2].clone();
+                    self.stack.push(b);
+                }
+
+                Instruction::Rot => {
+                    self.require(at, 3, "'rot' operation requires three operand")?;
+                    let c = self.stack.remove(self.stack.len() - 3);
+                    self.stack.push(c);
+                }
+
+                Instruction::Nip => {
+                    self.require(at, 2, "'nip' operation requires two operand")?;
+                    self.stack.remove(self.stack.len() - 2);
+                }
+
+                Instruction::Tuck => {
+                    self.require(at, 2, "'tuck' operation requires two operand")?;
+                    let a = self.stack[self.stack.len() - 1].clone();
+                    self.stack.insert(self.stack.len() - 2, a);
+                }
+
+                Instruction::Pick => {
+                    self.require(at, 1, "'pick' operation requires one operand")?;
+                    let depth = match self.stack.pop().unwrap() {
+                        Object::Integer(integer) if integer >= 0 => integer as usize,
                         _ => {
-                            self.invalid_type(&format!(
-                                "'*' is only usable with number in line {}",
-                                operation.line
+                            return Err(self.invalid_type(
+                                at,
+                                "'pick' is only usable with a non-negative integer",
                             ));
                         }
+                    };
+                    if depth >= self.stack.len() {
+                        return Err(
+                            self.stack_underflow(at, "'pick' can not reach deeper than the stack")
+                        );
                     }
 
-                    instruction_pointer += 1;
+                    let a = self.stack[self.stack.len() - 1 - depth].clone();
+                    self.stack.push(a);
                 }
 
-                OperationType::Division => {
-                    if self.stack.len() < 2 {
-                        self.stack_underflow(&format!(
-                            "'/' operation requires two operand in line {}",
-                            operation.line
-                        ));
-                    }
+                Instruction::Depth => {
+                    self.stack.push(Object::Integer(self.stack.len() as i64));
+                }
 
-                    let a = self.stack.pop().unwrap();
-                    let b = self.stack.pop().unwrap();
-                    match (a, b) {
-                        (Object::Number(x), Object::Number(y)) => {
-                            self.stack.push(Object::Number(y / x));
+                Instruction::Int => {
+                    self.require(at, 1, "'int' operation requires one operand")?;
+                    let object = match self.stack.pop().unwrap() {
+                        object @ (Object::Integer(_) | Object::BigInt(_)) => object,
+                        Object::Number(number) => match BigInt::from_f64(number) {
+                            Some(integer) => integer.into(),
+                            None => {
+                                return Err(self.invalid_type(
+                                    at,
+                                    "'int' can not turn NaN or infinity into an integer",
+                                ));
+                            }
+                        },
+                        _ => return Err(self.invalid_type(at, "'int' is only usable with number")),
+                    };
+                    self.stack.push(object);
+                }
+
+                Instruction::Float => {
+                    self.require(at, 1, "'float' operation requires one operand")?;
+                    let number = match float(&self.stack.pop().unwrap()) {
+                        Some(number) => number,
+                        None => {
+                            return Err(self.invalid_type(at, "'float' is only usable with number"))
                         }
+                    };
+                    self.stack.push(Object::Number(number));
+                }
+
+                Instruction::Nop => {}
+
+                Instruction::JumpUnless(block) => {
+                    let keyword = match self.program[at].op_type {
+                        OperationType::Then => "then",
+                        _ => "do",
+                    };
+                    self.require(
+                        at,
+                        1,
+                        &format!("'{}' operation requires one operand", keyword),
+                    )?;
+                    match self.stack.pop().unwrap() {
+                        Object::Boolean(Boolean::True) => {}
+                        Object::Boolean(Boolean::False) => instruction_pointer = block as usize,
                         _ => {
-                            self.invalid_type(&format!(
-                                "'/' is only usable with number in line {}",
-                                operation.line
+                            return Err(self.invalid_type(
+                                at,
+                                &format!("'{}' can use with only boolean", keyword),
                             ));
                         }
                     }
+                }
 
-                    instruction_pointer += 1;
+                Instruction::Jump(block) => instruction_pointer = block as usize,
+
+                Instruction::For(_) | Instruction::Each => {
+                    let instruction = self.bytecode.instructions[at];
+                    let keyword = match instruction {
+                        Instruction::For(_) => "for",
+                        _ => "each",
+                    };
+                    self.require(
+                        at,
+                        1,
+                        &format!("'{}' operation requires one operand", keyword),
+                    )?;
+                    let mut items = match self.stack.pop().unwrap() {
+                        Object::List(items) => items,
+                        _ => {
+                            return Err(self.invalid_type(
+                                at,
+                                &format!("'{}' can use with only list", keyword),
+                            ));
+                        }
+                    };
+                    items.reverse();
+                    self.iterations.push(Iteration {
+                        items,
+                        slot: match instruction {
+                            Instruction::For(slot) => Some(slot as usize),
+                            _ => None,
+                        },
+                    });
                 }
 
-                OperationType::Modulus => {
-                    if self.stack.len() < 2 {
-                        self.stack_underflow(&format!(
-                            "'/' operation requires two operand in line {}",
-                            operation.line
-                        ));
+                Instruction::Next(block) => {
+                    let iteration = match self.iterations.last_mut() {
+                        Some(iteration) => iteration,
+                        None => {
+                            return Err(self.invalid_reference(
+                                at,
+                                "'do' does not belong to a running 'for' or 'each'",
+                            ));
+                        }
+                    };
+                    match (iteration.items.pop(), iteration.slot) {
+                        (Some(item), Some(slot)) => self.assign(slot, item),
+                        (Some(item), None) => self.stack.push(item),
+                        (None, _) => {
+                            self.iterations.pop();
+                            instruction_pointer = block as usize;
+                        }
                     }
+                }
 
-                    let a = self.stack.pop().unwrap();
-                    let b = self.stack.pop().unwrap();
-                    match (a, b) {
-                        (Object::Number(x), Object::Number(y)) => {
-                            self.stack.push(Object::Number(y % x));
+                Instruction::Include => {
+                    self.require(at, 1, "'include' operation requires one operand")?;
+                    match self.stack.pop().unwrap() {
+                        Object::String(path) => {
+                            instruction_pointer = self.include(&path, at)?;
                         }
                         _ => {
-                            self.invalid_type(&format!(
-                                "'/' is only usable with number in line {}",
-                                operation.line
-                            ));
+                            return Err(self.invalid_type(at, "'include' can use with only string"));
                         }
                     }
+                }
 
-                    instruction_pointer += 1;
+                Instruction::Return(return_to) => {
+                    self.loader.finish();
+                    self.include_depths.pop();
+                    instruction_pointer = return_to as usize;
                 }
 
-                OperationType::Equal => {
-                    if self.stack.len() < 2 {
-                        self.stack_underflow(&format!(
-                            "'=' operation requires two operand in line {}",
-                            operation.line
-                        ));
-                    }
+                Instruction::Halt => instruction_pointer = self.bytecode.instructions.len(),
 
-                    let a = self.stack.pop().unwrap();
-                    let b = self.stack.pop().unwrap();
-                    if a == b {
-                        self.stack.push(Object::Boolean(Boolean::True));
-                    } else {
-                        self.stack.push(Object::Boolean(Boolean::False));
+                Instruction::Read => {
+                    let mut object = String::new();
+                    stdin()
+                        .read_line(&mut object)
+                        .map_err(|err| StuckError::Io(self.location(at), err.to_string()))?;
+                    let object = object.trim();
+                    self.stack
+                        .push(number(object).unwrap_or_else(|| Object::String(object.to_string())));
+                }
+
+                Instruction::Write | Instruction::WriteLine | Instruction::EWrite => {
+                    let instruction = self.bytecode.instructions[at];
+                    let keyword = match instruction {
+                        Instruction::Write => "write",
+                        Instruction::WriteLine => "writeln",
+                        _ => "ewrite",
+                    };
+                    self.require(
+                        at,
+                        1,
+                        &format!("'{}' operation requires one operand", keyword),
+                    )?;
+
+                    let text = match text(&self.stack.pop().unwrap()) {
+                        Some(text) => text,
+                        None => {
+                            return Err(self.invalid_type(
+                                at,
+                                &format!(
+                                    "'{}' can use with only number, string, boolean, list and map",
+                                    keyword
+                                ),
+                            ));
+                        }
+                    };
+                    match instruction {
+                        Instruction::Write => write!(self.output, "{}", text),
+                        Instruction::WriteLine => writeln!(self.output, "{}", text),
+                        _ => {
+                            eprint!("{}", text);
+                            Ok(())
+                        }
                     }
+                    .map_err(|err| StuckError::Io(self.location(at), err.to_string()))?;
+                }
 
-                    instruction_pointer += 1;
+                Instruction::Flush => {
+                    self.output
+                        .flush()
+                        .map_err(|err| StuckError::Io(self.location(at), err.to_string()))?;
                 }
 
-                OperationType::Greater => {
-                    if self.stack.len() < 2 {
-                        self.stack_underflow(&format!(
-                            "'>' operation requires two operand in line {}",
-                            operation.line
+                Instruction::OpenList => self.lists.push(self.stack.len()),
+
+                Instruction::CloseList => {
+                    let start = match self.lists.pop() {
+                        Some(start) => start,
+                        None => {
+                            return Err(
+                                self.invalid_reference(at, "'}' does not have a matching '{'")
+                            );
+                        }
+                    };
+                    if self.stack.len() < start {
+                        return Err(self.stack_underflow(
+                            at,
+                            "a list can not take values that were on the stack before its '{'",
                         ));
                     }
+                    let items = self.stack.split_off(start);
+                    self.stack.push(Object::List(items));
+                }
 
-                    let a = self.stack.pop().unwrap();
-                    let b = self.stack.pop().unwrap();
-                    match (a, b) {
-                        (Object::Number(x), Object::Number(y)) => {
-                            if y > x {
-                                self.stack.push(Object::Boolean(Boolean::True));
-                            } else {
-                                self.stack.push(Object::Boolean(Boolean::False));
-                            }
+                Instruction::Len => {
+                    self.require(at, 1, "'len' operation requires one operand")?;
+                    match self.stack.pop().unwrap() {
+                        Object::List(items) => self.stack.push(Object::Integer(items.len() as i64)),
+                        Object::Map(entries) => {
+                            self.stack.push(Object::Integer(entries.len() as i64))
                         }
+                        Object::String(string) => self
+                            .stack
+                            .push(Object::Integer(string.chars().count() as i64)),
                         _ => {
-                            self.invalid_type(&format!(
-                                "'>' is only usable with number in line {}",
-                                operation.line
-                            ));
+                            return Err(self.invalid_type(
+                                at,
+                                "'len' is only usable with list, map and string",
+                            ))
                         }
                     }
-
-                    instruction_pointer += 1;
                 }
 
-                OperationType::Less => {
-                    if self.stack.len() < 2 {
-                        self.stack_underflow(&format!(
-                            "'>' operation requires two operand in line {}",
-                            operation.line
-                        ));
-                    }
-
-                    let a = self.stack.pop().unwrap();
-                    let b = self.stack.pop().unwrap();
-                    match (a, b) {
-                        (Object::Number(x), Object::Number(y)) => {
-                            if y < x {
-                                self.stack.push(Object::Boolean(Boolean::True));
-                            } else {
-                                self.stack.push(Object::Boolean(Boolean::False));
+                Instruction::Get => {
+                    self.require(at, 2, "'get' operation requires two operand")?;
+                    let index = self.stack.pop().unwrap();
+                    match (self.stack.pop().unwrap(), index) {
+                        (Object::List(mut items), Object::Integer(index)) => {
+                            let index = self.index(at, "get", index, items.len())?;
+                            self.stack.push(items.swap_remove(index));
+                        }
+                        (Object::Map(mut entries), key) => {
+                            match entries.remove(&self.key(at, "get", &key)?) {
+                                Some(value) => self.stack.push(value),
+                                None => {
+                                    return Err(self.missing_key(
+                                        at,
+                                        &format!(
+                                            "'get' can not find {} in the map",
+                                            key.literal().unwrap_or_default()
+                                        ),
+                                    ));
+                                }
                             }
                         }
                         _ => {
-                            self.invalid_type(&format!(
-                                "'<' is only usable with number in line {}",
-                                operation.line
+                            return Err(self.invalid_type(
+                                at,
+                                "'get' is only usable with a list and an integer or a map and a key",
                             ));
                         }
                     }
-
-                    instruction_pointer += 1;
                 }
 
-                OperationType::Not => {
-                    if self.stack.len() < 1 {
-                        self.stack_underflow(&format!(
-                            "'!' operator requires one operand in line {}",
-                            operation.line
-                        ));
-                    }
-
-                    let a = self.stack.pop().unwrap();
-                    match a {
-                        Object::Boolean(x) => match x {
-                            Boolean::True => self.stack.push(Object::Boolean(Boolean::False)),
-                            Boolean::False => self.stack.push(Object::Boolean(Boolean::True)),
-                        },
+                Instruction::Set => {
+                    self.require(at, 3, "'set' operation requires three operand")?;
+                    let item = self.stack.pop().unwrap();
+                    let index = self.stack.pop().unwrap();
+                    match (self.stack.pop().unwrap(), index) {
+                        (Object::List(mut items), Object::Integer(index)) => {
+                            let index = self.index(at, "set", index, items.len())?;
+                            items[index] = item;
+                            self.stack.push(Object::List(items));
+                        }
                         _ => {
-                            self.invalid_type(&format!(
-                                "'!' is only usable with boolean in line {}",
-                                operation.line
+                            return Err(self.invalid_type(
+                                at,
+                                "'set' is only usable with a list, an integer and an item",
                             ));
                         }
                     }
-
-                    instruction_pointer += 1;
                 }
 
-                OperationType::And => {
-                    if self.stack.len() < 2 {
-                        self.stack_underflow(&format!(
-                            "'&' operation requires two operand in line {}",
-                            operation.line
-                        ));
+                Instruction::Push => {
+                    self.require(at, 2, "'push' operation requires two operand")?;
+                    let item = self.stack.pop().unwrap();
+                    match self.stack.pop().unwrap() {
+                        Object::List(mut items) => {
+                            items.push(item);
+                            self.stack.push(Object::List(items));
+                        }
+                        _ => {
+                            return Err(self.invalid_type(
+                                at,
+                                "'push' is only usable with a list and an item",
+                            ));
+                        }
                     }
+                }
 
-                    let a = self.stack.pop().unwrap();
-                    let b = self.stack.pop().unwrap();
-                    match (a, b) {
-                        (Object::Boolean(x), Object::Boolean(y)) => match (x, y) {
-                            (Boolean::True, Boolean::True) => {
-                                self.stack.push(Object::Boolean(Boolean::True))
+                Instruction::Pop => {
+                    self.require(at, 1, "'pop' operation requires one operand")?;
+                    match self.stack.pop().unwrap() {
+                        Object::List(mut items) => match items.pop() {
+                            Some(item) => {
+                                self.stack.push(Object::List(items));
+                                self.stack.push(item);
+                            }
+                            None => {
+                                return Err(self.index_out_of_range(
+                                    at,
+                                    "'pop' can not take an item from an empty list",
+                                ));
                             }
-
-                            _ => self.stack.push(Object::Boolean(Boolean::False)),
                         },
+                        _ => return Err(self.invalid_type(at, "'pop' is only usable with list")),
+                    }
+                }
 
+                Instruction::Slice => {
+                    self.require(at, 3, "'slice' operation requires three operand")?;
+                    let end = self.stack.pop().unwrap();
+                    let start = self.stack.pop().unwrap();
+                    match (self.stack.pop().unwrap(), start, end) {
+                        (Object::List(items), Object::Integer(start), Object::Integer(end)) => {
+                            if start < 0 || end < start || end as usize > items.len() {
+                                return Err(self.index_out_of_range(
+                                    at,
+                                    &format!(
+                                        "'slice' can not take the items from {} to {} \
+                                        of a list with {} item(s)",
+                                        start,
+                                        end,
+                                        items.len()
+                                    ),
+                                ));
+                            }
+                            let items = items[start as usize..end as usize].to_vec();
+                            self.stack.push(Object::List(items));
+                        }
                         _ => {
-                            self.invalid_type(&format!(
-                                "'&' is only usable with boolean in line {}",
-                                operation.line
+                            return Err(self.invalid_type(
+                                at,
+                                "'slice' is only usable with a list and two integers",
                             ));
                         }
                     }
-
-                    instruction_pointer += 1;
                 }
 
-                OperationType::Or => {
-                    if self.stack.len() < 2 {
-                        self.stack_underflow(&format!(
-                            "'|' operation requires two operand in line {}",
-                            operation.line
-                        ));
-                    }
-
+                Instruction::Concat => {
+                    self.require(at, 2, "'concat' operation requires two operand")?;
                     let a = self.stack.pop().unwrap();
-                    let b = self.stack.pop().unwrap();
-                    match (a, b) {
-                        (Object::Boolean(x), Object::Boolean(y)) => match (x, y) {
-                            (Boolean::False, Boolean::False) => {
-                                self.stack.push(Object::Boolean(Boolean::False))
-                            }
-
-                            _ => self.stack.push(Object::Boolean(Boolean::True)),
-                        },
-
+                    match (self.stack.pop().unwrap(), a) {
+                        (Object::List(mut items), Object::List(other)) => {
+                            items.extend(other);
+                            self.stack.push(Object::List(items));
+                        }
+                        (Object::String(mut string), Object::String(other)) => {
+                            string.push_str(&other);
+                            self.stack.push(Object::String(string));
+                        }
                         _ => {
-                            self.invalid_type(&format!(
-                                "'|' is only usable with boolean in line {}",
-                                operation.line
+                            return Err(self.invalid_type(
+                                at,
+                                "'concat' is only usable with two lists or two strings",
                             ));
                         }
                     }
-
-                    instruction_pointer += 1;
                 }
 
-                OperationType::If => {
-                    instruction_pointer += 1;
-                }
+                Instruction::Map => self.stack.push(Object::Map(BTreeMap::new())),
 
-                OperationType::Then => {
-                    if self.stack.len() < 1 {
-                        self.stack_underflow(&format!(
-                            "'then' operation requires one operand in line {}",
-                            operation.line
-                        ));
+                Instruction::Put => {
+                    self.require(at, 3, "'put' operation requires three operand")?;
+                    let value = self.stack.pop().unwrap();
+                    let key = self.stack.pop().unwrap();
+                    match self.stack.pop().unwrap() {
+                        Object::Map(mut entries) => {
+                            entries.insert(self.key(at, "put", &key)?, value);
+                            self.stack.push(Object::Map(entries));
+                        }
+                        _ => {
+                            return Err(self.invalid_type(
+                                at,
+                                "'put' is only usable with a map, a key and a value",
+                            ));
+                        }
                     }
+                }
 
-                    let a = self.stack.pop().unwrap();
-                    match a {
-                        Object::Boolean(boolean) => {
-                            if let Some(end_block) = &operation.operand {
-                                match end_block {
-                                    Object::Reference(number) => match boolean {
-                                        Boolean::False => instruction_pointer = number.to_owned(),
-                                        Boolean::True => instruction_pointer += 1,
-                                    },
-                                    _ => {
-                                        self.invalid_reference(&format!(
-                                            "invalid reference for 'then' (expected integer) in line {}",
-                                            operation.line
-                                        ));
-                                    }
-                                }
-                            } else {
-                                self.invalid_reference(&format!(
-                                    "'then' does not have reference to it's 'end' or 'else' block in line {}",
-                                    operation.line
-                                ));
-                            }
+                Instruction::Has => {
+                    self.require(at, 2, "'has' operation requires two operand")?;
+                    let key = self.stack.pop().unwrap();
+                    match self.stack.pop().unwrap() {
+                        Object::Map(entries) => {
+                            let has = entries.contains_key(&self.key(at, "has", &key)?);
+                            self.push_boolean(has);
                         }
                         _ => {
-                            self.invalid_type(&format!(
-                                "'then' doesn't have a reference associated with it in line {}",
-                                operation.line
-                            ));
+                            return Err(
+                                self.invalid_type(at, "'has' is only usable with a map and a key")
+                            );
                         }
                     }
                 }
 
-                OperationType::Else => {
-                    if let Some(end_block) = &operation.operand {
-                        match end_block {
-                            Object::Reference(number) => {
-                                instruction_pointer = number.to_owned();
-                            }
-                            _ => {
-                                self.invalid_reference(&format!(
-                                    "invalid reference for 'else' (expected integer) in line {}",
-                                    operation.line
-                                ));
-                            }
+                Instruction::Del => {
+                    self.require(at, 2, "'del' operation requires two operand")?;
+                    let key = self.stack.pop().unwrap();
+                    match self.stack.pop().unwrap() {
+                        Object::Map(mut entries) => {
+                            entries.remove(&self.key(at, "del", &key)?);
+                            self.stack.push(Object::Map(entries));
+                        }
+                        _ => {
+                            return Err(
+                                self.invalid_type(at, "'del' is only usable with a map and a key")
+                            );
                         }
-                    } else {
-                        self.invalid_reference(&format!(
-                            "'else' does not have reference to it's 'end' block in line {}",
-                            operation.line
-                        ));
                     }
                 }
 
-                OperationType::While => {
-                    instruction_pointer += 1;
+                Instruction::Keys => {
+                    self.require(at, 1, "'keys' operation requires one operand")?;
+                    match self.stack.pop().unwrap() {
+                        Object::Map(entries) => {
+                            let keys = entries.keys().map(Key::to_object).collect();
+                            self.stack.push(Object::List(keys));
+                        }
+                        _ => return Err(self.invalid_type(at, "'keys' is only usable with map")),
+                    }
                 }
 
-                OperationType::Do => {
-                    if self.stack.len() < 1 {
-                        self.stack_underflow(&format!(
-                            "'do' operation requires one operand in line {}",
-                            operation.line
-                        ));
+                Instruction::Values => {
+                    self.require(at, 1, "'values' operation requires one operand")?;
+                    match self.stack.pop().unwrap() {
+                        Object::Map(entries) => {
+                            let values = entries.into_values().collect();
+                            self.stack.push(Object::List(values));
+                        }
+                        _ => return Err(self.invalid_type(at, "'values' is only usable with map")),
                     }
+                }
 
-                    let a = self.stack.pop().unwrap();
-                    match a {
-                        Object::Boolean(boolean) => {
-                            if let Some(end_block) = &operation.operand {
-                                match end_block {
-                                    Object::Reference(number) => match boolean {
-                                        Boolean::False => instruction_pointer = number.to_owned(),
-                                        Boolean::True => instruction_pointer += 1,
-                                    },
-                                    _ => {
-                                        self.invalid_reference(&format!(
-                                            "invalid reference for 'do' (expected integer) in line {}",
-                                            operation.line
-                                        ));
-                                    }
-                                }
-                            } else {
-                                self.invalid_reference(&format!(
-                                    "'do' does not have reference to it's 'end' block in line {}",
-                                    operation.line
+                Instruction::Substr => {
+                    self.require(at, 3, "'substr' operation requires three operand")?;
+                    let end = self.stack.pop().unwrap();
+                    let start = self.stack.pop().unwrap();
+                    match (self.stack.pop().unwrap(), start, end) {
+                        (Object::String(string), Object::Integer(start), Object::Integer(end)) => {
+                            let length = string.chars().count();
+                            if start < 0 || end < start || end as usize > length {
+                                return Err(self.index_out_of_range(
+                                    at,
+                                    &format!(
+                                        "'substr' can not take the characters from {} to {} \
+                                        of a string with {} character(s)",
+                                        start, end, length
+                                    ),
                                 ));
                             }
+                            let substring = string
+                                .chars()
+                                .skip(start as usize)
+                                .take((end - start) as usize)
+                                .collect();
+                            self.stack.push(Object::String(substring));
                         }
                         _ => {
-                            self.invalid_type(&format!(
-                                "'then' doesn't have a reference associated with it in line {}",
-                                operation.line
+                            return Err(self.invalid_type(
+                                at,
+                                "'substr' is only usable with a string and two integers",
                             ));
                         }
                     }
                 }
 
-                OperationType::End => {
-                    if let Some(starting_block) = &operation.operand {
-                        match starting_block {
-                            Object::Reference(number) => {
-                                instruction_pointer = number.to_owned();
-                            }
-                            invalid_type => {
-                                self.invalid_type(&format!(
-                                    "can not use '{:?}' with 'end' (expected integer) in line {}",
-                                    invalid_type, operation.line
-                                ));
-                            }
-                        }
-                    } else {
-                        self.invalid_reference(&format!(
-                            "'end' doesn't have a reference associated with it in line {}",
-                            operation.line
-                        ));
-                    }
+                Instruction::Index => {
+                    let (string, needle) = self.pop_strings(at, "index")?;
+                    let index = match string.find(&needle) {
+                        Some(byte) => string[..byte].chars().count() as i64,
+                        None => -1,
+                    };
+                    self.stack.push(Object::Integer(index));
                 }
 
-                OperationType::Include => {
-                    if self.stack.len() < 1 {
-                        self.stack_underflow(&format!(
-                            "'include' operation requires one operand in line {}",
-                            operation.line
+                Instruction::Split => {
+                    let (string, separator) = self.pop_strings(at, "split")?;
+                    if separator.is_empty() {
+                        return Err(self.invalid_value(
+                            at,
+                            "'split' can not split by an empty string (see 'chars')",
                         ));
                     }
+                    let parts = string
+                        .split(&separator)
+                        .map(|part| Object::String(part.to_string()))
+                        .collect();
+                    self.stack.push(Object::List(parts));
+                }
 
-                    let a = self.stack.pop().unwrap();
-                    match a {
-                        Object::String(_) => {
-                            todo!()
+                Instruction::Join => {
+                    self.require(at, 2, "'join' operation requires two operand")?;
+                    let separator = self.stack.pop().unwrap();
+                    match (self.stack.pop().unwrap(), separator) {
+                        (Object::List(items), Object::String(separator)) => {
+                            let parts: Option<Vec<String>> = items.iter().map(text).collect();
+                            match parts {
+                                Some(parts) => {
+                                    self.stack.push(Object::String(parts.join(&separator)))
+                                }
+                                None => {
+                                    return Err(
+                                        self.invalid_type(at, "'join' can not join functions")
+                                    );
+                                }
+                            }
                         }
                         _ => {
-                            self.invalid_type(&format!(
-                                "'write' can use with only string in line {}",
-                                operation.line
+                            return Err(self.invalid_type(
+                                at,
+                                "'join' is only usable with a list and a string",
                             ));
                         }
                     }
+                }
 
-                    instruction_pointer += 1;
+                Instruction::Upper | Instruction::Lower | Instruction::Trim => {
+                    let instruction = self.bytecode.instructions[at];
+                    let keyword = match instruction {
+                        Instruction::Upper => "upper",
+                        Instruction::Lower => "lower",
+                        _ => "trim",
+                    };
+                    let string = self.pop_string(at, keyword)?;
+                    let string = match instruction {
+                        Instruction::Upper => string.to_uppercase(),
+                        Instruction::Lower => string.to_lowercase(),
+                        _ => string.trim().to_string(),
+                    };
+                    self.stack.push(Object::String(string));
                 }
 
-                OperationType::Read => {
-                    let mut object = String::new();
-                    stdin().read_line(&mut object).unwrap_or_else(|err| {
-                        eprintln!("Error: {:#?}", err);
-                        exit(1);
-                    });
-                    let object = object.trim();
-                    if let Ok(number) = object.parse() {
-                        self.stack.push(Object::Number(number));
-                    } else {
-                        self.stack.push(Object::String(object.to_string()));
+                Instruction::Replace => {
+                    self.require(at, 3, "'replace' operation requires three operand")?;
+                    let to = self.stack.pop().unwrap();
+                    let from = self.stack.pop().unwrap();
+                    match (self.stack.pop().unwrap(), from, to) {
+                        (Object::String(_), Object::String(from), Object::String(_))
+                            if from.is_empty() =>
+                        {
+                            return Err(
+                                self.invalid_value(at, "'replace' can not replace an empty string")
+                            );
+                        }
+                        (Object::String(string), Object::String(from), Object::String(to)) => {
+                            self.stack.push(Object::String(string.replace(&from, &to)));
+                        }
+                        _ => {
+                            return Err(self
+                                .invalid_type(at, "'replace' is only usable with three strings"));
+                        }
                     }
+                }
 
-                    instruction_pointer += 1;
+                Instruction::StartsWith => {
+                    let (string, prefix) = self.pop_strings(at, "startswith")?;
+                    self.push_boolean(string.starts_with(&prefix));
                 }
 
-                OperationType::Write => {
-                    if self.stack.len() < 1 {
-                        self.stack_underflow(&format!(
-                            "'write' operation requires one operand in line {}",
-                            operation.line
-                        ));
+                Instruction::EndsWith => {
+                    let (string, suffix) = self.pop_strings(at, "endswith")?;
+                    self.push_boolean(string.ends_with(&suffix));
+                }
+
+                Instruction::Chars => {
+                    let string = self.pop_string(at, "chars")?;
+                    let characters = string
+                        .chars()
+                        .map(|character| Object::String(character.to_string()))
+                        .collect();
+                    self.stack.push(Object::List(characters));
+                }
+
+                Instruction::ToStr => {
+                    self.require(at, 1, "'tostr' operation requires one operand")?;
+                    match text(&self.stack.pop().unwrap()) {
+                        Some(string) => self.stack.push(Object::String(string)),
+                        None => {
+                            return Err(self.invalid_type(
+                                at,
+                                "'tostr' can not turn a function into a string",
+                            ));
+                        }
                     }
+                }
 
-                    let a = self.stack.pop().unwrap();
-                    match a {
-                        Object::String(string) => print!("{}", string),
-                        Object::Number(number) => print!("{}", number),
-                        Object::Boolean(boolean) => match boolean {
-                            Boolean::True => print!("true"),
-                            Boolean::False => print!("false"),
+                Instruction::ToNum => {
+                    self.require(at, 1, "'tonum' operation requires one operand")?;
+                    let object = match self.stack.pop().unwrap() {
+                        object @ (Object::Integer(_) | Object::BigInt(_) | Object::Number(_)) => {
+                            object
+                        }
+                        Object::String(string) => match number(string.trim()) {
+                            Some(object) => object,
+                            None => {
+                                return Err(self.invalid_value(
+                                    at,
+                                    &format!("'tonum' can not turn {:?} into a number", string),
+                                ));
+                            }
                         },
                         _ => {
-                            self.invalid_type(&format!(
-                                "'write' can use with only number, string and boolean in line {}",
-                                operation.line
+                            return Err(self.invalid_type(
+                                at,
+                                "'tonum' is only usable with string and number",
                             ));
                         }
+                    };
+                    self.stack.push(object);
+                }
+
+                Instruction::Format => {
+                    self.require(at, 1, "'format' operation requires one operand")?;
+                    let template = match self.stack.pop().unwrap() {
+                        Object::String(template) => template,
+                        _ => {
+                            return Err(self.invalid_type(
+                                at,
+                                "'format' is only usable with a string template",
+                            ));
+                        }
+                    };
+                    let pieces = template::parse(&template).map_err(|message| {
+                        self.invalid_value(at, &format!("'format' got a bad template: {}", message))
+                    })?;
+                    let placeholders = pieces
+                        .iter()
+                        .filter(|piece| matches!(piece, Piece::Value(_)))
+                        .count();
+                    if self.stack.len() < placeholders {
+                        return Err(self.stack_underflow(
+                            at,
+                            &format!(
+                                "'format' needs {} value(s) for its template, found {}",
+                                placeholders,
+                                self.stack.len()
+                            ),
+                        ));
                     }
 
-                    instruction_pointer += 1;
+                    let mut values = self.stack.split_off(self.stack.len() - placeholders);
+                    values.reverse();
+                    let mut string = String::new();
+                    for piece in pieces {
+                        match piece {
+                            Piece::Text(text) => string.push_str(&text),
+                            Piece::Value(spec) => {
+                                let value = values.pop().unwrap();
+                                let number = float(&value).is_some();
+                                let text = match (spec.precision, float(&value)) {
+                                    (Some(precision), Some(number)) => {
+                                        format!("{:.*}", precision, number)
+                                    }
+                                    (Some(_), None) => {
+                                        return Err(self.invalid_type(
+                                            at,
+                                            "'format' can only use a precision with numbers",
+                                        ));
+                                    }
+                                    (None, _) => match text(&value) {
+                                        Some(text) => text,
+                                        None => {
+                                            return Err(self.invalid_type(
+                                                at,
+                                                "'format' can not format functions",
+                                            ));
+                                        }
+                                    },
+                                };
+                                string.push_str(&template::pad(&text, &spec, number));
+                            }
+                        }
+                    }
+                    self.stack.push(Object::String(string));
+                }
+
+                Instruction::Interpolate(slot) => match self.lookup(slot as usize) {
+                    Some(Object::Function(_)) => {
+                        return Err(self.invalid_type(
+                            at,
+                            &format!(
+                                "'{}' is a function, which can not be interpolated",
+                                self.symbols.name(slot as usize)
+                            ),
+                        ));
+                    }
+                    Some(object) => {
+                        let object = object.clone();
+                        self.stack.push(object);
+                    }
+                    None => {
+                        return Err(self.undefined_variable(
+                            at,
+                            &format!(
+                                "variable '{}' does not exist",
+                                self.symbols.name(slot as usize)
+                            ),
+                        ));
+                    }
+                },
+
+                Instruction::Invalid => {
+                    return Err(self.invalid_reference(
+                        at,
+                        &format!(
+                            "'{:?}' does not have a valid operand",
+                            self.program[at].op_type
+                        ),
+                    ));
                 }
             }
         }
+
+        Ok(())
     }
 }
 
 impl Interpreter {
-    fn stack_underflow(&self, message: &str) {
-        self.error("StackUnderflow", message);
+    fn stack_underflow(&self, at: usize, message: &str) -> StuckError {
+        StuckError::StackUnderflow(self.location(at), message.to_string())
+    }
+
+    fn undefined_variable(&self, at: usize, message: &str) -> StuckError {
+        StuckError::UndefinedVariable(self.location(at), message.to_string())
+    }
+
+    fn invalid_reference(&self, at: usize, message: &str) -> StuckError {
+        StuckError::InvalidReference(self.location(at), message.to_string())
+    }
+
+    fn invalid_type(&self, at: usize, message: &str) -> StuckError {
+        StuckError::InvalidType(self.location(at), message.to_string())
     }
 
-    fn undefined_variable(&self, message: &str) {
-        self.error("UndefinedVariable", message);
+    fn invalid_variable_type(&self, at: usize, message: &str) -> StuckError {
+        StuckError::InvalidVariableType(self.location(at), message.to_string())
     }
 
-    fn invalid_reference(&self, message: &str) {
-        self.error("InvalidReference", message);
+    fn stack_overflow(&self, at: usize, message: &str) -> StuckError {
+        StuckError::StackOverflow(self.location(at), message.to_string())
     }
 
-    fn invalid_type(&self, message: &str) {
-        self.error("InvalidType", message);
+    fn index_out_of_range(&self, at: usize, message: &str) -> StuckError {
+        StuckError::IndexOutOfRange(self.location(at), message.to_string())
     }
 
-    fn invalid_variable_type(&self, message: &str) {
-        self.error("InvalidVariableType", message);
+    fn invalid_value(&self, at: usize, message: &str) -> StuckError {
+        StuckError::InvalidValue(self.location(at), message.to_string())
     }
 
-    fn error(&self, e_type: &str, message: &str) {
-        eprintln!("{}: {}.", e_type, message);
-        exit(1);
+    fn missing_key(&self, at: usize, message: &str) -> StuckError {
+        StuckError::MissingKey(self.location(at), message.to_string())
+    }
+
+    fn division_by_zero(&self, at: usize, message: &str) -> StuckError {
+        StuckError::DivisionByZero(self.location(at), message.to_string())
+    }
+
+    fn arity_mismatch(&self, at: usize, message: &str) -> StuckError {
+        StuckError::ArityMismatch(self.location(at), message.to_string())
+    }
+
+    /// Like `location`, but without the source (traces only need to say where calls are).
+    fn call_site(&self, at: usize) -> Location {
+        self.loader.call_site(self.program[at].span)
+    }
+}
+
+/// `=`: like `==`, except that integers and floats are equal when they have the same value.
+fn equal(a: &Object, b: &Object) -> bool {
+    match (a, b) {
+        (Object::Integer(integer), Object::Number(number))
+        | (Object::Number(number), Object::Integer(integer)) => *integer as f64 == *number,
+        (Object::BigInt(integer), Object::Number(number))
+        | (Object::Number(number), Object::BigInt(integer)) => integer.to_f64() == *number,
+        (Object::List(a), Object::List(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b))
+        }
+        (Object::Map(a), Object::Map(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|((a_key, a), (b_key, b))| a_key == b_key && equal(a, b))
+        }
+        _ => a == b,
+    }
+}
+
+/// Reads an integer, a big integer or a float (in that order), like `read` and `tonum` do.
+/// Stuck has no literals for infinity and NaN, so `inf`, `nan` and floats too big to be
+/// finite aren't numbers either.
+fn number(text: &str) -> Option<Object> {
+    if let Ok(integer) = text.parse() {
+        Some(Object::Integer(integer))
+    } else if let Some(integer) = BigInt::from_decimal(text) {
+        Some(Object::BigInt(integer))
+    } else {
+        text.parse()
+            .ok()
+            .filter(|number: &f64| number.is_finite())
+            .map(Object::Number)
+    }
+}
+
+/// What `write` writes for `object`, if it can be written.
+fn text(object: &Object) -> Option<String> {
+    match object {
+        Object::String(string) => Some(string.clone()),
+        object => object.literal(),
+    }
+}
+
+/// Runs an integer operator on `i64`s, falling back to `BigInt`s when `checked` overflows.
+fn promote(
+    x: i64,
+    y: i64,
+    checked: fn(i64, i64) -> Option<i64>,
+    big: fn(&BigInt, &BigInt) -> BigInt,
+) -> Object {
+    match checked(x, y) {
+        Some(integer) => Object::Integer(integer),
+        None => big(&BigInt::from(x), &BigInt::from(y)).into(),
+    }
+}
+
+fn big_integer(object: &Object) -> Option<BigInt> {
+    match object {
+        Object::Integer(integer) => Some(BigInt::from(*integer)),
+        Object::BigInt(integer) => Some(integer.clone()),
+        _ => None,
+    }
+}
+
+fn float(object: &Object) -> Option<f64> {
+    match object {
+        Object::Integer(integer) => Some(*integer as f64),
+        Object::BigInt(integer) => Some(integer.to_f64()),
+        Object::Number(number) => Some(*number),
+        _ => None,
     }
 }
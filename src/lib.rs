@@ -0,0 +1,37 @@
+//! Stuck is a stack based programing language.
+//!
+//! This crate holds everything the `stuck` binary is made of, so other tools can
+//! tokenize, interpret or compile stuck programs themselves. Most embedders only
+//! need an [`Interpreter`]:
+//!
+//! ```
+//! use stuck::{Interpreter, Object};
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter.set_variable("width", Object::Number(4.0));
+//! interpreter.run("width 10 * @area\n").unwrap();
+//!
+//! assert_eq!(interpreter.get_variable("area"), Some(&Object::Number(40.0)));
+//! ```
+
+mod bigint;
+mod bytecode;
+#[doc(hidden)]
+pub mod cli;
+mod compiler;
+mod debugger;
+mod editor;
+mod error;
+mod help;
+mod interpreter;
+mod loader;
+mod object;
+mod operation;
+mod repl;
+mod template;
+mod tokenizer;
+
+pub use bigint::BigInt;
+pub use error::{Call, Location, Snippet, StuckError};
+pub use interpreter::{Debugger, Interpreter, DEFAULT_MAX_CALL_DEPTH};
+pub use object::{Boolean, Function, Key, Object, Signature};
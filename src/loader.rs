@@ -0,0 +1,256 @@
+use std::{
+    collections::HashSet,
+    fs::{canonicalize, read_to_string},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{Location, StuckError},
+    object::Object,
+    operation::{Operation, Span},
+    tokenizer::Tokenizer,
+};
+
+/// The files a program is made of. The interpreter and the compiler both load them through
+/// here, so `include`s are resolved the same way: relative to the including file, only once
+/// per file and without cycles.
+#[derive(Default)]
+pub struct Loader {
+    /// Every file gets an entry of its own, even if it has the same path as an earlier one,
+    /// because the operations from before still point into their source.
+    files: Vec<PathBuf>,
+    /// The source of every file in `files`, for pointing at where errors are.
+    sources: Vec<String>,
+    included: HashSet<PathBuf>,
+    /// The files that are being included, outermost first, for reporting include cycles.
+    including: Vec<(usize, PathBuf)>,
+}
+
+impl Loader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tokenizes `source`, which was read from `path`, as the outermost file of a program.
+    /// Its operations are relocated to start at `offset`.
+    pub fn load(
+        &mut self,
+        path: PathBuf,
+        source: &str,
+        offset: usize,
+    ) -> Result<Vec<Operation>, StuckError> {
+        let file = self.register(path, source);
+        self.including.clear();
+        if let Ok(canonical_path) = canonicalize(&self.files[file]) {
+            self.included.insert(canonical_path.clone());
+            self.including.push((file, canonical_path));
+        }
+        self.tokenize(file, offset)
+    }
+
+    /// Loads the file an `include` (at `span`) refers to, with its operations relocated to
+    /// start at `offset` and its `EndOfFile` pointing at `return_to`. Returns `None` if the
+    /// file was included before.
+    ///
+    /// The file counts as being included (so including it again is a cycle) until `finish`,
+    /// and only counts as included once it's finished, so a file that fails can be included
+    /// again.
+    pub fn include(
+        &mut self,
+        path: &str,
+        span: Span,
+        offset: usize,
+        return_to: usize,
+    ) -> Result<Option<Vec<Operation>>, StuckError> {
+        let canonical_path = self.resolve(path, span)?;
+        let path = self.path(path, span);
+
+        if self
+            .including
+            .iter()
+            .any(|(_, including_path)| including_path == &canonical_path)
+        {
+            let mut cycle: Vec<String> = self
+                .including
+                .iter()
+                .map(|(file, _)| format!("`{}`", self.files[*file].display()))
+                .collect();
+            cycle.push(format!("`{}`", path.display()));
+            return Err(
+                self.invalid_include(span, &format!("include cycle {}", cycle.join(" -> ")))
+            );
+        }
+
+        if self.included.contains(&canonical_path) {
+            return Ok(None);
+        }
+
+        let mut source = read_to_string(&path).map_err(|err| {
+            self.invalid_include(
+                span,
+                &format!("can not include `{}` ({})", path.display(), err),
+            )
+        })?;
+        source.push('\n');
+
+        let file = self.register(path, &source);
+        let mut operations = self.tokenize(file, offset)?;
+        if let Some(end_of_file) = operations.last_mut() {
+            end_of_file.operand = Some(Object::Reference(return_to));
+        }
+
+        self.including.push((file, canonical_path));
+
+        Ok(Some(operations))
+    }
+
+    /// Marks the innermost file that's being included as done.
+    pub fn finish(&mut self) {
+        if let Some((_, canonical_path)) = self.including.pop() {
+            self.included.insert(canonical_path);
+        }
+    }
+
+    /// Returns the canonical path of the file an `include` (at `span`) refers to.
+    pub fn resolve(&self, path: &str, span: Span) -> Result<PathBuf, StuckError> {
+        let path = self.path(path, span);
+        canonicalize(&path).map_err(|err| {
+            self.invalid_include(
+                span,
+                &format!("can not include `{}` ({})", path.display(), err),
+            )
+        })
+    }
+
+    /// Returns the source of `file`.
+    pub fn source(&self, file: usize) -> &str {
+        &self.sources[file]
+    }
+
+    /// Where `span` is, pointing into its source.
+    pub fn location(&self, span: Span) -> Location {
+        let source = &self.sources[span.file];
+        self.call_site(span)
+            .pointing_into(source, span.width(source))
+    }
+
+    /// Like `location`, but without the source (traces only need to say where calls are).
+    pub fn call_site(&self, span: Span) -> Location {
+        Location::new(
+            &self.files[span.file].display().to_string(),
+            span.line,
+            span.column,
+        )
+    }
+}
+
+impl Loader {
+    /// Include paths are relative to the including file.
+    fn path(&self, path: &str, span: Span) -> PathBuf {
+        self.files[span.file]
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(path)
+    }
+
+    fn register(&mut self, path: PathBuf, source: &str) -> usize {
+        self.files.push(path);
+        self.sources.push(source.to_string());
+        self.files.len() - 1
+    }
+
+    fn tokenize(&self, file: usize, offset: usize) -> Result<Vec<Operation>, StuckError> {
+        let mut tokenizer = Tokenizer::new(
+            &self.sources[file],
+            file,
+            &self.files[file].display().to_string(),
+        );
+        Ok(tokenizer
+            .scan_tokens()?
+            .into_iter()
+            .map(|operation| operation.relocate(offset))
+            .collect())
+    }
+
+    fn invalid_include(&self, span: Span, message: &str) -> StuckError {
+        StuckError::InvalidInclude(self.location(span), message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    /// Writes `files` (paths and sources) into a new directory and returns it.
+    fn directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory =
+            env::temp_dir().join(format!("stuck-loader-{}-{}", name, std::process::id()));
+        for (path, source) in files {
+            let path = directory.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+        directory
+    }
+
+    /// Loads `main.stk` from `directory` and returns the span of its first `include`.
+    fn load_main(loader: &mut Loader, directory: &Path) -> Span {
+        let path = directory.join("main.stk");
+        let source = fs::read_to_string(&path).unwrap();
+        let operations = loader.load(path, &source, 0).unwrap();
+        operations[1].span
+    }
+
+    #[test]
+    fn includes_are_relative_to_the_including_file_and_loaded_once() {
+        let directory = directory(
+            "relative",
+            &[
+                ("main.stk", "\"lib/a.stk\" include\n"),
+                ("lib/a.stk", "1\n"),
+            ],
+        );
+        let mut loader = Loader::new();
+        let span = load_main(&mut loader, &directory);
+
+        let operations = loader.include("lib/a.stk", span, 10, 2).unwrap().unwrap();
+        assert_eq!(operations[0].operand, Some(Object::Integer(1)));
+        assert_eq!(operations[1].operand, Some(Object::Reference(2)));
+        loader.finish();
+
+        assert!(loader.include("lib/a.stk", span, 12, 2).unwrap().is_none());
+    }
+
+    #[test]
+    fn include_cycles_are_errors() {
+        let directory = directory(
+            "cycle",
+            &[
+                ("main.stk", "\"a.stk\" include\n"),
+                ("a.stk", "\"main.stk\" include\n"),
+            ],
+        );
+        let mut loader = Loader::new();
+        let span = load_main(&mut loader, &directory);
+        let operations = loader.include("a.stk", span, 3, 2).unwrap().unwrap();
+
+        let error = loader
+            .include("main.stk", operations[1].span, 6, 5)
+            .unwrap_err();
+        assert!(matches!(error, StuckError::InvalidInclude(..)));
+        assert!(error.message().starts_with("include cycle"), "{}", error);
+    }
+
+    #[test]
+    fn missing_files_are_errors() {
+        let directory = directory("missing", &[("main.stk", "\"nope.stk\" include\n")]);
+        let mut loader = Loader::new();
+        let span = load_main(&mut loader, &directory);
+
+        let error = loader.include("nope.stk", span, 3, 2).unwrap_err();
+        assert!(matches!(error, StuckError::InvalidInclude(..)));
+        assert_eq!(error.location().line, 1);
+    }
+}
@@ -1,37 +1,178 @@
+use std::{cmp::Ordering, collections::BTreeMap};
+
+use crate::bigint::BigInt;
+
+/// A boolean, as `true` and `false` push it.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Boolean {
     True,
     False,
 }
 
+/// A function declared with `[ ... ]`. It can only be called by the interpreter that
+/// declared it.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Function {
-    pub opening_block: usize,
-    pub called_from: usize,
+    /// Where its `[` is in the program.
+    pub(crate) opening_block: usize,
 }
 
 impl Function {
-    pub fn new(opening_block: usize) -> Self {
-        Self {
-            opening_block,
-            called_from: 0,
-        }
+    pub(crate) fn new(opening_block: usize) -> Self {
+        Self { opening_block }
     }
+}
 
-    pub fn assign_called_from(opening_block: usize, called_from: usize) -> Self {
-        Self {
-            opening_block,
-            called_from,
-        }
-    }
+/// The `a b -- c` part of `[ a b -- c | ... ]`: parameter names and how many results are left.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Signature {
+    pub parameters: Vec<String>,
+    pub results: usize,
 }
 
+/// A value on the stack or in a variable.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Object {
+    /// A name in the source. Only operations have these.
     Identifier(String),
+    Integer(i64),
+    /// An integer that doesn't fit in an `Integer`. Integer arithmetic that overflows
+    /// promotes to it, and results that fit again are turned back into an `Integer`.
+    BigInt(BigInt),
+    /// A float. Literals with a `.` are floats, everything else is an `Integer`.
     Number(f64),
     String(String),
     Boolean(Boolean),
+    List(Vec<Object>),
+    /// Keys are kept in order: numbers (smallest first) before strings.
+    Map(BTreeMap<Key, Object>),
     Function(Function),
+    /// Only operations have these.
+    Signature(Signature),
+    /// Where a block's other end is in the program. Only operations have these.
     Reference(usize),
 }
+
+impl Object {
+    /// How the object is written inside of lists and maps (and by the REPL): like `write`
+    /// writes it, but with strings quoted. Functions can't be written.
+    pub fn literal(&self) -> Option<String> {
+        match self {
+            Object::String(string) => Some(format!("{:?}", string)),
+            Object::Integer(integer) => Some(integer.to_string()),
+            Object::BigInt(integer) => Some(integer.to_string()),
+            Object::Number(number) => Some(number.to_string()),
+            Object::Boolean(Boolean::True) => Some("true".to_string()),
+            Object::Boolean(Boolean::False) => Some("false".to_string()),
+            Object::List(items) => {
+                let mut text = String::from("{");
+                for item in items {
+                    text.push(' ');
+                    text.push_str(&item.literal()?);
+                }
+                text.push_str(" }");
+                Some(text)
+            }
+            Object::Map(entries) if entries.is_empty() => Some("{:}".to_string()),
+            Object::Map(entries) => {
+                let entries: Option<Vec<String>> = entries
+                    .iter()
+                    .map(|(key, value)| {
+                        Some(format!(
+                            "{}: {}",
+                            key.to_object().literal()?,
+                            value.literal()?
+                        ))
+                    })
+                    .collect();
+                Some(format!("{{ {} }}", entries?.join(", ")))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<BigInt> for Object {
+    fn from(integer: BigInt) -> Self {
+        match integer.to_i64() {
+            Some(integer) => Object::Integer(integer),
+            None => Object::BigInt(integer),
+        }
+    }
+}
+
+/// A map key: a string or a number.
+///
+/// Whole floats are stored as integers, so `1` and `1.0` are the same key just like they
+/// are `=`. That leaves `Float` with numbers that have a fraction, which are never equal
+/// to an integer.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Key {
+    Integer(i64),
+    BigInt(BigInt),
+    Float(f64),
+    String(String),
+}
+
+impl Key {
+    /// Turns `object` into a key, if it's a string or a number (other than NaN).
+    pub fn new(object: &Object) -> Option<Self> {
+        match object {
+            Object::String(string) => Some(Key::String(string.clone())),
+            Object::Integer(integer) => Some(Key::Integer(*integer)),
+            Object::BigInt(integer) => Some(Key::BigInt(integer.clone())),
+            Object::Number(number) if number.fract() == 0.0 => {
+                match Object::from(BigInt::from_f64(*number)?) {
+                    Object::Integer(integer) => Some(Key::Integer(integer)),
+                    Object::BigInt(integer) => Some(Key::BigInt(integer)),
+                    _ => None,
+                }
+            }
+            Object::Number(number) if !number.is_nan() => Some(Key::Float(*number)),
+            _ => None,
+        }
+    }
+
+    /// Turns the key back into the object it stands for.
+    pub fn to_object(&self) -> Object {
+        match self {
+            Key::Integer(integer) => Object::Integer(*integer),
+            Key::BigInt(integer) => Object::BigInt(integer.clone()),
+            Key::Float(number) => Object::Number(*number),
+            Key::String(string) => Object::String(string.clone()),
+        }
+    }
+
+    fn to_f64(&self) -> f64 {
+        match self {
+            Key::Integer(integer) => *integer as f64,
+            Key::BigInt(integer) => integer.to_f64(),
+            Key::Float(number) => *number,
+            Key::String(_) => f64::NAN,
+        }
+    }
+}
+
+// `Key::new` never makes a NaN key.
+impl Eq for Key {}
+
+impl Ord for Key {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Key::String(a), Key::String(b)) => a.cmp(b),
+            (Key::String(_), _) => Ordering::Greater,
+            (_, Key::String(_)) => Ordering::Less,
+            (Key::Integer(a), Key::Integer(b)) => a.cmp(b),
+            (Key::Integer(a), Key::BigInt(b)) => BigInt::from(*a).cmp(b),
+            (Key::BigInt(a), Key::Integer(b)) => a.cmp(&BigInt::from(*b)),
+            (Key::BigInt(a), Key::BigInt(b)) => a.cmp(b),
+            (a, b) => a.to_f64().total_cmp(&b.to_f64()),
+        }
+    }
+}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
@@ -1,6 +1,6 @@
 use crate::object::Object;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum OperationType {
     Identifier,
 
@@ -10,8 +10,10 @@ pub enum OperationType {
     False,
 
     Function,
+    Signature,
 
     Assignment,
+    Global,
 
     Plus,
     Minus,
@@ -27,31 +29,112 @@ pub enum OperationType {
     And,
     Or,
 
+    Dup,
+    Drop,
+    Swap,
+    Over,
+    Rot,
+    Nip,
+    Tuck,
+    Pick,
+    Depth,
+
+    Int,
+    Float,
+
+    OpenList,
+    CloseList,
+    Len,
+    Get,
+    Set,
+    Push,
+    Pop,
+    Slice,
+    Concat,
+
+    Map,
+    Put,
+    Has,
+    Del,
+    Keys,
+    Values,
+
+    Substr,
+    Index,
+    Split,
+    Join,
+    Upper,
+    Lower,
+    Trim,
+    Replace,
+    StartsWith,
+    EndsWith,
+    Chars,
+    ToStr,
+    ToNum,
+    Format,
+    /// A `{name}` placeholder in a string literal.
+    Interpolate,
+
     If,
     Then,
     Else,
     While,
+    For,
+    Each,
     Do,
     End,
 
     Include,
+    EndOfFile,
     Read,
     Write,
+    WriteLine,
+    EWrite,
+    Flush,
+}
+
+/// Where an operation comes from: the bytes `start..end` of file `file`, which begin at
+/// `line` and `column` (counted in characters, from 1).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub file: usize,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// How many characters of the span are on its first line.
+    pub fn width(&self, source: &str) -> usize {
+        source
+            .get(self.start..self.end)
+            .and_then(|text| text.lines().next())
+            .map_or(0, |text| text.chars().count())
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Operation {
     pub op_type: OperationType,
     pub operand: Option<Object>,
-    pub line: usize,
+    pub span: Span,
 }
 
 impl Operation {
-    pub fn new(op_type: OperationType, operand: Option<Object>, line: usize) -> Self {
+    pub fn new(op_type: OperationType, operand: Option<Object>, span: Span) -> Self {
         Self {
             op_type,
             operand,
-            line,
+            span,
+        }
+    }
+
+    pub fn relocate(mut self, offset: usize) -> Self {
+        if let Some(Object::Reference(reference)) = self.operand {
+            self.operand = Some(Object::Reference(reference + offset));
         }
+        self
     }
 }
@@ -0,0 +1,372 @@
+use std::{
+    env,
+    fs::write,
+    io::{self, stdin, ErrorKind, IsTerminal},
+    path::PathBuf,
+    time::Instant,
+};
+
+use crate::{
+    editor::Editor, help, interpreter::Interpreter, object::Object, operation::Operation,
+    tokenizer::Tokenizer,
+};
+
+const PROMPT: &str = "stuck :> ";
+/// Shown while a block, list or `"""` string from the lines before is still open.
+const CONTINUATION_PROMPT: &str = "   ... > ";
+
+/// Where `:save` and `:restore` keep the session unless they're told otherwise.
+const SESSION_FILE: &str = "session.stk";
+
+/// The commands, for completing them.
+const COMMANDS: &[&str] = &[
+    ":stack", ":vars", ":clear", ":reset", ":load", ":ops", ":time", ":save", ":restore", ":help",
+    ":quit",
+];
+
+const HELP: &str = "\
+:stack          shows the stack (it's also shown after every input)
+:vars           shows every variable
+:clear          empties the stack
+:reset          forgets the stack, variables and included files
+:load <file>    runs a file
+:ops [source]   shows the operations `source` (or the last input) is tokenized into
+:time <source>  runs `source` and shows how long it took
+:save [file]    writes every variable to `file` (session.stk by default)
+:restore [file] declares the variables saved in `file` (session.stk by default) again
+:help [word]    shows this page, or what a builtin word or a variable is
+:quit, :q       leaves the repl (so does Ctrl-D)";
+
+/// The interactive prompt `stuck` starts without arguments. Everything entered runs on the
+/// same interpreter, so the stack and variables carry over from one input to the next.
+///
+/// Lines starting with `:` are commands for inspecting the interpreter (see `:help`). An
+/// input that fails changes neither the stack nor the variables, so everything declared
+/// before it is kept.
+pub struct Repl {
+    interpreter: Interpreter,
+    editor: Editor,
+    /// The last input that was run, for `:ops`.
+    last_source: String,
+    /// Whether a person is typing (rather than input being piped in), so the stack is shown.
+    interactive: bool,
+    keywords: Vec<String>,
+}
+
+impl Repl {
+    /// Creates a REPL that keeps its history in `~/.stuck_history`.
+    pub fn new(interpreter: Interpreter) -> Self {
+        let history_file =
+            env::var_os("HOME").map(|home| PathBuf::from(home).join(".stuck_history"));
+        Self {
+            interpreter,
+            editor: Editor::new(history_file),
+            last_source: String::new(),
+            interactive: stdin().is_terminal(),
+            keywords: Tokenizer::keywords(),
+        }
+    }
+
+    /// Reads and runs input until it ends (Ctrl-D or `:quit`). Errors in the input are
+    /// reported and reading goes on, only failing to read ends it early.
+    pub fn run(&mut self) -> io::Result<()> {
+        let mut source = String::new();
+        loop {
+            let prompt = if source.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION_PROMPT
+            };
+            self.editor.set_completions(self.completions());
+            let line = match self.editor.read_line(prompt) {
+                Ok(Some(line)) => line,
+                // Whatever is left is run anyway, so its error gets reported.
+                Ok(None) if source.is_empty() => return Ok(()),
+                Ok(None) => {
+                    self.evaluate(&source);
+                    return Ok(());
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => {
+                    source.clear();
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            self.editor.add_history(&line);
+
+            if source.is_empty() {
+                if let Some(command) = line.trim_start().strip_prefix(':') {
+                    if !self.command(command) {
+                        return Ok(());
+                    }
+                    continue;
+                }
+            }
+
+            source.push_str(&line);
+            source.push('\n');
+            if !Tokenizer::is_unfinished(&source) {
+                self.evaluate(&source);
+                self.show_stack(false);
+                source.clear();
+            }
+        }
+    }
+}
+
+impl Repl {
+    fn evaluate(&mut self, source: &str) {
+        self.last_source = source.to_string();
+        if let Err(err) = self.interpreter.run(source) {
+            eprintln!("{}", err);
+        }
+    }
+
+    /// Runs a `:` command. Returns `false` if the REPL should stop.
+    fn command(&mut self, command: &str) -> bool {
+        let (name, argument) = match command.trim().split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (command.trim(), ""),
+        };
+
+        match name {
+            "stack" => self.show_stack(true),
+            "vars" => {
+                let variables = self.interpreter.variables();
+                if variables.is_empty() {
+                    println!("no variables");
+                }
+                for (name, object) in variables {
+                    println!("{} = {}", name, describe(object));
+                }
+            }
+            "clear" => self.interpreter.clear_stack(),
+            "reset" => self.interpreter.reset(),
+            "load" if !argument.is_empty() => {
+                if let Err(err) = self.interpreter.run_file(argument) {
+                    eprintln!("{}", err);
+                }
+                self.show_stack(false);
+            }
+            "ops" => {
+                let source = if argument.is_empty() {
+                    self.last_source.clone()
+                } else {
+                    format!("{}\n", argument)
+                };
+                match Tokenizer::new(&source, 0, "stdin").scan_tokens() {
+                    Ok(operations) => {
+                        for (index, operation) in operations.iter().enumerate() {
+                            println!("{:>4}  {}", index, show_operation(operation));
+                        }
+                    }
+                    Err(err) => eprintln!("{}", err),
+                }
+            }
+            "time" if !argument.is_empty() => {
+                let start = Instant::now();
+                self.evaluate(&format!("{}\n", argument));
+                let elapsed = start.elapsed();
+                self.show_stack(false);
+                println!("took {:?}", elapsed);
+            }
+            "save" => self.save(if argument.is_empty() {
+                SESSION_FILE
+            } else {
+                argument
+            }),
+            "restore" => {
+                let path = if argument.is_empty() {
+                    SESSION_FILE
+                } else {
+                    argument
+                };
+                if let Err(err) = self.interpreter.run_file(path) {
+                    eprintln!("{}", err);
+                }
+            }
+            "help" if argument.is_empty() => println!("{}", HELP),
+            "help" => self.help(argument),
+            "quit" | "q" => return false,
+            "load" | "time" => {
+                eprintln!("`:{}` needs an argument (see `:help`)", name)
+            }
+            _ => eprintln!("unknown command `:{}` (see `:help`)", name),
+        }
+
+        true
+    }
+
+    /// Keywords, variables and commands.
+    fn completions(&self) -> Vec<String> {
+        let mut completions = self.keywords.clone();
+        for (name, _) in self.interpreter.variables() {
+            completions.push(name.to_string());
+        }
+        completions.extend(COMMANDS.iter().map(|command| command.to_string()));
+        completions
+    }
+
+    /// Shows what `word` is: a builtin, a function (by its first line) or another variable.
+    fn help(&self, word: &str) {
+        if let Some(help) = help::builtin(word) {
+            println!("{}", help);
+            return;
+        }
+        match self.interpreter.get_variable(word) {
+            Some(Object::Function(function)) => {
+                let source = self
+                    .interpreter
+                    .function_source(function)
+                    .unwrap_or("[ ... ]");
+                match source.lines().nth(1) {
+                    Some(_) => println!("{}  {} ...", word, source.lines().next().unwrap_or("")),
+                    None => println!("{}  {}", word, source),
+                }
+            }
+            Some(object) => println!("{} = {}", word, describe(object)),
+            None => eprintln!("`{}` is not a builtin or a variable", word),
+        }
+    }
+
+    /// Writes every variable as a stuck program that declares it again.
+    fn save(&self, path: &str) {
+        let mut session = String::from(
+            "# A stuck session, written by `:save`. `:restore` declares its variables again.\n",
+        );
+        for (name, object) in self.interpreter.variables() {
+            match source(object, &self.interpreter) {
+                Some(source) => session.push_str(&format!("{} @{}\n", source, name)),
+                None => eprintln!("`{}` can not be saved", name),
+            }
+        }
+        if let Err(err) = write(path, session) {
+            eprintln!("can not save to `{}` ({})", path, err);
+        }
+    }
+
+    /// Shows the stack like `<2> 1 "two"`. Unless `always`, an empty stack isn't shown and
+    /// nothing is shown when the input isn't typed in.
+    fn show_stack(&self, always: bool) {
+        let stack = self.interpreter.stack();
+        if !always && (stack.is_empty() || !self.interactive) {
+            return;
+        }
+        println!("{}", stack_line(stack));
+    }
+}
+
+/// Shows a stack like `<2> 1 "two"`.
+pub(crate) fn stack_line(stack: &[Object]) -> String {
+    let mut line = format!("<{}>", stack.len());
+    for object in stack {
+        line.push(' ');
+        line.push_str(&describe(object));
+    }
+    line
+}
+
+pub(crate) fn describe(object: &Object) -> String {
+    match object {
+        Object::Function(_) => "<function>".to_string(),
+        object => object.literal().unwrap_or_else(|| format!("{:?}", object)),
+    }
+}
+
+/// Stuck source that pushes `object`, if there is any.
+fn source(object: &Object, interpreter: &Interpreter) -> Option<String> {
+    match object {
+        // There are no negative literals, so negative numbers are subtracted from zero.
+        Object::Integer(integer) if *integer < 0 => Some(format!("0 {} -", integer.unsigned_abs())),
+        Object::Integer(integer) => Some(integer.to_string()),
+        Object::BigInt(integer) => {
+            let integer = integer.to_string();
+            match integer.strip_prefix('-') {
+                Some(magnitude) => Some(format!("0 {} -", magnitude)),
+                None => Some(integer),
+            }
+        }
+        Object::Number(number) if number.is_finite() => {
+            // Floats need a `.`, or they'd come back as integers.
+            let mut magnitude = number.abs().to_string();
+            if !magnitude.contains('.') {
+                magnitude.push_str(".0");
+            }
+            if number.is_sign_negative() {
+                Some(format!("0.0 {} -", magnitude))
+            } else {
+                Some(magnitude)
+            }
+        }
+        Object::String(string) => {
+            // Braces are written as raw strings, so they aren't taken for placeholders.
+            let mut pieces = Vec::new();
+            let mut text = String::new();
+            for character in string.chars() {
+                if let '{' | '}' = character {
+                    if !text.is_empty() {
+                        pieces.push(format!("{:?}", std::mem::take(&mut text)));
+                    }
+                    pieces.push(format!("r\"{}\"", character));
+                } else {
+                    text.push(character);
+                }
+            }
+            if !text.is_empty() || pieces.is_empty() {
+                pieces.push(format!("{:?}", text));
+            }
+            let mut source = pieces[0].clone();
+            for piece in &pieces[1..] {
+                source.push_str(&format!(" {} +", piece));
+            }
+            Some(source)
+        }
+        Object::Boolean(_) => object.literal(),
+        Object::List(items) => {
+            let mut source = String::from("{");
+            for item in items {
+                source.push(' ');
+                source.push_str(&self::source(item, interpreter)?);
+            }
+            source.push_str(" }");
+            Some(source)
+        }
+        Object::Map(entries) => {
+            let mut source = String::from("map");
+            for (key, value) in entries {
+                source.push_str(&format!(
+                    " {} {} put",
+                    self::source(&key.to_object(), interpreter)?,
+                    self::source(value, interpreter)?
+                ));
+            }
+            Some(source)
+        }
+        Object::Function(function) => interpreter.function_source(function).map(str::to_string),
+        _ => None,
+    }
+}
+
+/// Shows an operation like `1:3  Plus` or `2:1  Identifier x`.
+pub(crate) fn show_operation(operation: &Operation) -> String {
+    let span = operation.span;
+    let mut text = format!(
+        "{:<8}{:?}",
+        format!("{}:{}", span.line, span.column),
+        operation.op_type
+    );
+    let operand = match &operation.operand {
+        Some(Object::Identifier(identifier)) => identifier.clone(),
+        Some(Object::Reference(reference)) => format!("-> {}", reference),
+        Some(Object::Signature(signature)) => format!(
+            "{} -- {}",
+            signature.parameters.join(" "),
+            signature.results
+        ),
+        Some(object) => describe(object),
+        None => return text,
+    };
+    text.push(' ');
+    text.push_str(&operand);
+    text
+}
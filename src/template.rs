@@ -0,0 +1,231 @@
+/// The widest a placeholder can pad its value to.
+pub const MAX_WIDTH: usize = 1_000;
+/// The most decimals a placeholder can show.
+pub const MAX_PRECISION: usize = 100;
+
+/// How a `{}` placeholder lays out its value: `{:[<^>][0][width][.precision]}`.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Spec {
+    pub align: Option<char>,
+    pub zero: bool,
+    pub width: usize,
+    pub precision: Option<usize>,
+}
+
+/// A piece of a `format` template.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Piece {
+    Text(String),
+    Value(Spec),
+}
+
+/// Splits a template into text and placeholders. `{{` and `}}` stand for `{` and `}`.
+pub fn parse(template: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut characters = template.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '{' if characters.peek() == Some(&'{') => {
+                characters.next();
+                text.push('{');
+            }
+            '}' if characters.peek() == Some(&'}') => {
+                characters.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match characters.next() {
+                        Some('}') => break,
+                        Some(character) => placeholder.push(character),
+                        None => return Err(format!("`{{{}` is never closed", placeholder)),
+                    }
+                }
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(Piece::Value(spec(&placeholder)?));
+            }
+            '}' => return Err("`}` must be written as `}}`".to_string()),
+            character => text.push(character),
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+
+    Ok(pieces)
+}
+
+/// Pads `text` to the width of `spec`. Numbers are right aligned unless `spec` says otherwise,
+/// everything else is left aligned.
+pub fn pad(text: &str, spec: &Spec, number: bool) -> String {
+    let length = text.chars().count();
+    if length >= spec.width {
+        return text.to_string();
+    }
+    let padding = spec.width - length;
+
+    if spec.zero && number && spec.align.is_none() {
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", text),
+        };
+        return format!("{}{}{}", sign, "0".repeat(padding), digits);
+    }
+
+    let align = spec.align.unwrap_or(if number { '>' } else { '<' });
+    let (before, after) = match align {
+        '<' => (0, padding),
+        '^' => (padding / 2, padding - padding / 2),
+        _ => (padding, 0),
+    };
+    format!("{}{}{}", " ".repeat(before), text, " ".repeat(after))
+}
+
+/// Parses what's between the braces of a placeholder. Widths and precisions are limited,
+/// so a template can't make the string it's formatted into arbitrarily big.
+fn spec(placeholder: &str) -> Result<Spec, String> {
+    let mut spec = Spec::default();
+    let mut rest = match placeholder.strip_prefix(':') {
+        Some(rest) => rest,
+        None if placeholder.is_empty() => return Ok(spec),
+        None => {
+            return Err(format!(
+                "can not understand the placeholder `{{{}}}`",
+                placeholder
+            ))
+        }
+    };
+
+    if let Some(align @ ('<' | '^' | '>')) = rest.chars().next() {
+        spec.align = Some(align);
+        rest = &rest[1..];
+    }
+    if let Some(digits) = rest.strip_prefix('0') {
+        spec.zero = true;
+        rest = digits;
+    }
+    let (width, precision) = match rest.split_once('.') {
+        Some((width, precision)) => (width, Some(precision)),
+        None => (rest, None),
+    };
+    if !width.is_empty() {
+        spec.width = limited(width, MAX_WIDTH, placeholder, "width")?;
+    }
+    if let Some(precision) = precision {
+        spec.precision = Some(limited(precision, MAX_PRECISION, placeholder, "precision")?);
+    }
+
+    Ok(spec)
+}
+
+/// Parses the width or precision of a placeholder, which can't be more than `limit`.
+fn limited(digits: &str, limit: usize, placeholder: &str, what: &str) -> Result<usize, String> {
+    if digits.is_empty() || !digits.bytes().all(|digit| digit.is_ascii_digit()) {
+        return Err(format!(
+            "can not understand the placeholder `{{{}}}`",
+            placeholder
+        ));
+    }
+    match digits.parse() {
+        Ok(number) if number <= limit => Ok(number),
+        _ => Err(format!(
+            "the {} of `{{{}}}` can not be more than {}",
+            what, placeholder, limit
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(placeholder: &str) -> Spec {
+        match parse(placeholder).unwrap().as_slice() {
+            [Piece::Value(spec)] => spec.clone(),
+            pieces => panic!("`{}` was parsed into {:?}", placeholder, pieces),
+        }
+    }
+
+    #[test]
+    fn splits_text_and_placeholders() {
+        assert_eq!(
+            parse("a {} b {{c}}").unwrap(),
+            vec![
+                Piece::Text("a ".to_string()),
+                Piece::Value(Spec::default()),
+                Piece::Text(" b {c}".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_alignment_zero_width_and_precision() {
+        assert_eq!(
+            value("{:>08.3}"),
+            Spec {
+                align: Some('>'),
+                zero: true,
+                width: 8,
+                precision: Some(3),
+            }
+        );
+        assert_eq!(value("{:^5}").align, Some('^'));
+        assert_eq!(value("{:.0}").precision, Some(0));
+    }
+
+    #[test]
+    fn rejects_bad_placeholders() {
+        for template in ["{x}", "{:abc}", "{:5.}", "{:.x}", "{:+5}", "{", "}", "{:5"] {
+            assert!(parse(template).is_err(), "`{}` was accepted", template);
+        }
+    }
+
+    #[test]
+    fn rejects_widths_and_precisions_over_the_limit() {
+        assert_eq!(value("{:1000}").width, MAX_WIDTH);
+        assert_eq!(value("{:.100}").precision, Some(MAX_PRECISION));
+        assert!(parse("{:1001}").is_err());
+        assert!(parse("{:.101}").is_err());
+        assert!(parse("{:99999999999}").is_err());
+        assert!(parse("{:.99999999999999999999999}").is_err());
+    }
+
+    #[test]
+    fn aligns_numbers_right_and_everything_else_left() {
+        let spec = value("{:5}");
+        assert_eq!(pad("7", &spec, true), "    7");
+        assert_eq!(pad("ab", &spec, false), "ab   ");
+    }
+
+    #[test]
+    fn follows_the_alignment() {
+        assert_eq!(pad("7", &value("{:<4}"), true), "7   ");
+        assert_eq!(pad("ab", &value("{:>4}"), false), "  ab");
+        assert_eq!(pad("ab", &value("{:^5}"), false), " ab  ");
+    }
+
+    #[test]
+    fn fills_numbers_with_zeros_after_the_sign() {
+        let spec = value("{:05}");
+        assert_eq!(pad("42", &spec, true), "00042");
+        assert_eq!(pad("-42", &spec, true), "-0042");
+        // Only numbers get zeros, and an alignment turns them off.
+        assert_eq!(pad("ab", &spec, false), "ab   ");
+        assert_eq!(pad("42", &value("{:<05}"), true), "42   ");
+    }
+
+    #[test]
+    fn counts_characters_rather_than_bytes() {
+        assert_eq!(pad("héllo", &value("{:6}"), false), "héllo ");
+    }
+
+    #[test]
+    fn leaves_text_that_is_wide_enough() {
+        assert_eq!(pad("123456", &value("{:3}"), true), "123456");
+    }
+}
@@ -1,45 +1,81 @@
-use std::{collections::HashMap, process::exit};
+use std::collections::HashMap;
 
 use crate::{
-    object::Object,
-    operation::{Operation, OperationType},
+    bigint::BigInt,
+    error::{Location, StuckError},
+    object::{Object, Signature},
+    operation::{Operation, OperationType, Span},
+    template,
 };
 
 pub struct Tokenizer {
-    source: Vec<u8>,
-    source_len: usize,
+    source: String,
     operations: Vec<Operation>,
     keywords: HashMap<String, OperationType>,
+    /// The byte offset of the character after `current_charecter`.
     index: usize,
+    /// The byte offset of `current_charecter`.
+    offset: usize,
     current_charecter: Option<char>,
     line_number: usize,
+    column: usize,
+    token_column: usize,
+    token_start: usize,
+    file: usize,
+    file_name: String,
+    /// Whether the last error was about the source ending too early.
+    unfinished: bool,
 }
 
 impl Tokenizer {
-    pub fn new(source: &str) -> Self {
+    pub fn new(source: &str, file: usize, file_name: &str) -> Self {
         Self {
-            source: source.as_bytes().to_vec(),
-            source_len: source.len(),
+            source: source.to_string(),
             operations: Vec::new(),
             keywords: HashMap::new(),
             index: 0,
+            offset: 0,
             current_charecter: None,
             line_number: 1,
+            column: 0,
+            token_column: 0,
+            token_start: 0,
+            file,
+            file_name: file_name.to_string(),
+            unfinished: false,
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Vec<Operation> {
+    pub fn scan_tokens(&mut self) -> Result<Vec<Operation>, StuckError> {
         self.init_keywords();
-        self.scan();
-        return self.crossreference_operations();
+        self.advance();
+        self.scan()?;
+        self.start_token();
+        self.push_operation(OperationType::EndOfFile, None);
+        self.crossreference_operations()
+    }
+
+    /// Returns every keyword, sorted.
+    pub fn keywords() -> Vec<String> {
+        let mut tokenizer = Self::new("", 0, "");
+        tokenizer.init_keywords();
+        let mut keywords: Vec<String> = tokenizer.keywords.into_keys().collect();
+        keywords.sort();
+        keywords
+    }
+
+    /// Whether `source` stops inside a block, a list or a `"""` string, so more lines could
+    /// still turn it into a valid program.
+    pub fn is_unfinished(source: &str) -> bool {
+        let mut tokenizer = Self::new(source, 0, "");
+        tokenizer.scan_tokens().is_err() && tokenizer.unfinished
     }
 }
 
 impl Tokenizer {
-    fn scan(&mut self) {
-        self.advance();
-
+    fn scan(&mut self) -> Result<(), StuckError> {
         while let Some(current_charecter) = self.current_charecter {
+            self.start_token();
             match current_charecter {
                 ' ' | '\t' | '\r' => {
                     self.advance();
@@ -55,157 +91,130 @@ impl Tokenizer {
 
                 '+' => {
                     self.advance();
-                    self.operations.push(Operation::new(
-                        OperationType::Plus,
-                        None,
-                        self.line_number,
-                    ));
+                    self.push_operation(OperationType::Plus, None);
                 }
 
                 '-' => {
                     self.advance();
-                    self.operations.push(Operation::new(
-                        OperationType::Minus,
-                        None,
-                        self.line_number,
-                    ));
+                    self.push_operation(OperationType::Minus, None);
                 }
 
                 '*' => {
                     self.advance();
-                    self.operations.push(Operation::new(
-                        OperationType::Multiplication,
-                        None,
-                        self.line_number,
-                    ));
+                    self.push_operation(OperationType::Multiplication, None);
                 }
 
                 '/' => {
                     self.advance();
-                    self.operations.push(Operation::new(
-                        OperationType::Division,
-                        None,
-                        self.line_number,
-                    ));
+                    self.push_operation(OperationType::Division, None);
                 }
 
                 '%' => {
                     self.advance();
-                    self.operations.push(Operation::new(
-                        OperationType::Modulus,
-                        None,
-                        self.line_number,
-                    ));
+                    self.push_operation(OperationType::Modulus, None);
                 }
 
                 '=' => {
                     self.advance();
-                    self.operations.push(Operation::new(
-                        OperationType::Equal,
-                        None,
-                        self.line_number,
-                    ));
+                    self.push_operation(OperationType::Equal, None);
                 }
 
                 '>' => {
                     self.advance();
-                    self.operations.push(Operation::new(
-                        OperationType::Greater,
-                        None,
-                        self.line_number,
-                    ));
+                    self.push_operation(OperationType::Greater, None);
                 }
 
                 '<' => {
                     self.advance();
-                    self.operations.push(Operation::new(
-                        OperationType::Less,
-                        None,
-                        self.line_number,
-                    ));
+                    self.push_operation(OperationType::Less, None);
                 }
 
                 '!' => {
                     self.advance();
-                    self.operations.push(Operation::new(
-                        OperationType::Not,
-                        None,
-                        self.line_number,
-                    ));
+                    self.push_operation(OperationType::Not, None);
                 }
 
                 '&' => {
                     self.advance();
-                    self.operations.push(Operation::new(
-                        OperationType::And,
-                        None,
-                        self.line_number,
-                    ));
+                    self.push_operation(OperationType::And, None);
                 }
 
                 '|' => {
                     self.advance();
-                    self.operations
-                        .push(Operation::new(OperationType::Or, None, self.line_number));
+                    self.push_operation(OperationType::Or, None);
+                }
+
+                '{' => {
+                    self.advance();
+                    self.push_operation(OperationType::OpenList, None);
+                }
+
+                '}' => {
+                    self.advance();
+                    self.push_operation(OperationType::CloseList, None);
                 }
 
                 '@' => {
                     self.advance();
-                    self.operations.push(Operation::new(
-                        OperationType::Assignment,
-                        None,
-                        self.line_number,
-                    ));
+                    self.push_operation(OperationType::Assignment, None);
                 }
 
                 '"' => {
                     self.advance();
-                    self.make_string();
+                    self.make_string(false)?;
+                }
+
+                'r' if self.peek() == Some('"') => {
+                    self.advance();
+                    self.advance();
+                    self.make_string(true)?;
                 }
 
                 '[' => {
                     self.advance();
-                    self.operations.push(Operation::new(
-                        OperationType::Function,
-                        None,
-                        self.line_number,
-                    ));
-                    self.make_fuction();
+                    self.push_operation(OperationType::Function, None);
+                    self.make_signature()?;
                 }
 
                 ']' => {
                     self.advance();
-                    self.operations.push(Operation::new(
-                        OperationType::Function,
-                        None,
-                        self.line_number,
-                    ));
+                    self.push_operation(OperationType::Function, None);
                 }
 
                 token => {
-                    if token.is_digit(10) {
-                        self.make_number();
+                    if token.is_ascii_digit() {
+                        self.make_number()?;
                     } else if token.is_alphabetic() {
-                        self.make_identifier();
+                        self.make_identifier()?;
                     } else {
-                        self.error(&format!("invalid token `{}`", token));
+                        return Err(self.error(
+                            &format!("invalid token `{}`", token),
+                            self.line_number,
+                            self.column,
+                        ));
                     }
                 }
             }
         }
+
+        Ok(())
     }
 
-    fn crossreference_operations(&mut self) -> Vec<Operation> {
+    fn crossreference_operations(&mut self) -> Result<Vec<Operation>, StuckError> {
         let mut block_references = Vec::new();
+        let mut lists = Vec::new();
         let mut crossreferened_operations = self.operations.clone();
 
         for (operation_index, operation) in self.operations.iter().enumerate() {
+            let span = operation.span;
             match operation.op_type {
                 OperationType::If => block_references.push(operation_index),
 
                 OperationType::Then => block_references.push(operation_index),
 
-                OperationType::While => block_references.push(operation_index),
+                OperationType::While | OperationType::For | OperationType::Each => {
+                    block_references.push(operation_index)
+                }
 
                 OperationType::Do => block_references.push(operation_index),
 
@@ -218,8 +227,12 @@ impl Tokenizer {
                                     Some(Object::Reference(operation_index));
                             }
 
-                            invalid_block => self
-                                .error(&format!("can't end `fuction` with `{:?}`", invalid_block)),
+                            invalid_block => {
+                                return Err(self.error_at(
+                                    &format!("can't end `function` with `{:?}`", invalid_block),
+                                    span,
+                                ))
+                            }
                         }
                     } else {
                         block_references.push(operation_index);
@@ -241,27 +254,39 @@ impl Tokenizer {
                                         }
 
                                         OperationType::Else => {
+                                            // Until the chain's `end` is known, every `else`
+                                            // points back at the previous one.
                                             crossreferened_operations[then_block].operand =
                                                 Some(Object::Reference(operation_index + 1));
-                                            crossreferened_operations[if_else_block].operand =
-                                                Some(Object::Reference(operation_index + 1));
+                                            crossreferened_operations[operation_index].operand =
+                                                Some(Object::Reference(if_else_block));
                                             block_references.push(operation_index);
                                         }
 
-                                        invalid_block => self.error(&format!(
-                                            "can't end `else` with `{:?}`",
-                                            invalid_block
-                                        )),
+                                        invalid_block => {
+                                            return Err(self.error_at(
+                                                &format!(
+                                                    "can't end `else` with `{:?}`",
+                                                    invalid_block
+                                                ),
+                                                span,
+                                            ))
+                                        }
                                     }
+                                } else {
+                                    return Err(self.error_at("unexpected `then`", span));
                                 }
                             }
 
                             invalid_block => {
-                                self.error(&format!("can't end `else` with `{:?}`", invalid_block))
+                                return Err(self.error_at(
+                                    &format!("can't end `else` with `{:?}`", invalid_block),
+                                    span,
+                                ))
                             }
                         }
                     } else {
-                        self.error(&format!("invalid `else`"));
+                        return Err(self.error_at("invalid `else`", span));
                     }
                 }
 
@@ -280,19 +305,33 @@ impl Tokenizer {
                                                 Some(Object::Reference(operation_index + 1));
                                         }
 
-                                        invalid_block => self.error(&format!(
-                                            "can't end `end` with `{:?}`",
-                                            invalid_block
-                                        )),
+                                        invalid_block => {
+                                            return Err(self.error_at(
+                                                &format!(
+                                                    "can't end `end` with `{:?}`",
+                                                    invalid_block
+                                                ),
+                                                span,
+                                            ))
+                                        }
                                     }
                                 } else {
-                                    self.error(&format!("unexpected `then`"));
+                                    return Err(self.error_at("unexpected `then`", span));
                                 }
                             }
 
                             OperationType::Else => {
-                                crossreferened_operations[opening_block].operand =
-                                    Some(Object::Reference(operation_index + 1));
+                                let mut else_block = Some(opening_block);
+                                while let Some(block) = else_block {
+                                    else_block = match crossreferened_operations[block].operand {
+                                        Some(Object::Reference(previous_else)) => {
+                                            Some(previous_else)
+                                        }
+                                        _ => None,
+                                    };
+                                    crossreferened_operations[block].operand =
+                                        Some(Object::Reference(operation_index + 1));
+                                }
                                 crossreferened_operations[operation_index].operand =
                                     Some(Object::Reference(operation_index + 1));
                             }
@@ -308,39 +347,89 @@ impl Tokenizer {
                                                 Some(Object::Reference(while_block));
                                         }
 
-                                        invalid_block => self.error(&format!(
-                                            "can't end `end` with `{:?}`",
-                                            invalid_block
-                                        )),
+                                        // `for` and `each` only start the loop, every
+                                        // iteration starts at `do`.
+                                        OperationType::For | OperationType::Each => {
+                                            crossreferened_operations[opening_block].operand =
+                                                Some(Object::Reference(operation_index + 1));
+                                            crossreferened_operations[operation_index].operand =
+                                                Some(Object::Reference(opening_block));
+                                        }
+
+                                        invalid_block => {
+                                            return Err(self.error_at(
+                                                &format!(
+                                                    "can't end `end` with `{:?}`",
+                                                    invalid_block
+                                                ),
+                                                span,
+                                            ))
+                                        }
                                     }
                                 } else {
-                                    self.error(&format!("unexpected `do`"));
+                                    return Err(self.error_at("unexpected `do`", span));
                                 }
                             }
 
                             invalid_block => {
-                                self.error(&format!("can't end `end` with `{:?}`", invalid_block))
+                                return Err(self.error_at(
+                                    &format!("can't end `end` with `{:?}`", invalid_block),
+                                    span,
+                                ))
                             }
                         }
                     } else {
-                        self.error(&format!("unexpexted `end`"));
+                        return Err(self.error_at("unexpected `end`", span));
                     }
                 }
 
+                OperationType::OpenList => lists.push(operation_index),
+
+                OperationType::CloseList => {
+                    lists
+                        .pop()
+                        .ok_or_else(|| self.error_at("unexpected `}`", span))?;
+                }
+
                 _ => {}
             }
         }
 
-        crossreferened_operations
+        if let Some(open_list) = lists.pop() {
+            self.unfinished = true;
+            return Err(self.error_at("unclosed `{`", self.operations[open_list].span));
+        }
+        if let Some(open_block) = block_references.pop() {
+            let operation = &self.operations[open_block];
+            let closing = match operation.op_type {
+                OperationType::Function => "]",
+                _ => "end",
+            };
+            self.unfinished = true;
+            return Err(self.error_at(
+                &format!("this block is never closed with `{}`", closing),
+                operation.span,
+            ));
+        }
+
+        Ok(crossreferened_operations)
     }
 
     fn advance(&mut self) {
-        if self.index < self.source_len {
-            self.current_charecter = Some(self.source[self.index] as char);
-            self.index += 1;
-        } else {
-            self.current_charecter = None;
+        if self.current_charecter == Some('\n') {
+            self.column = 0;
         }
+        self.offset = self.index;
+        self.current_charecter = self.peek();
+        if let Some(current_charecter) = self.current_charecter {
+            self.index += current_charecter.len_utf8();
+            self.column += 1;
+        }
+    }
+
+    /// The character after the current one.
+    fn peek(&self) -> Option<char> {
+        self.source[self.index..].chars().next()
     }
 
     fn make_comment(&mut self) {
@@ -353,94 +442,426 @@ impl Tokenizer {
         }
     }
 
-    fn make_number(&mut self) {
+    fn make_number(&mut self) -> Result<(), StuckError> {
         let mut number = String::new();
         while let Some(current_charecter) = self.current_charecter {
-            if current_charecter.is_digit(10) || current_charecter == '.' {
+            if current_charecter.is_ascii_digit() || current_charecter == '.' {
                 number.push(current_charecter);
                 self.advance();
             } else {
-                let number = number.parse::<f64>().unwrap_or_else(|_| {
-                    self.error(&format!("Can't convert `{}` to a number", number));
-                    exit(1);
-                });
-                self.operations.push(Operation::new(
-                    OperationType::Number,
-                    Some(Object::Number(number)),
+                break;
+            }
+        }
+
+        let object = if number.contains('.') {
+            number.parse().map(Object::Number).map_err(|_| {
+                self.error(
+                    &format!("Can't convert `{}` to a number", number),
                     self.line_number,
-                ));
-                return;
+                    self.token_column,
+                )
+            })?
+        } else {
+            match number.parse() {
+                Ok(integer) => Object::Integer(integer),
+                Err(_) => BigInt::from_decimal(&number)
+                    .map(Object::BigInt)
+                    .ok_or_else(|| {
+                        self.error(
+                            &format!("Can't convert `{}` to a number", number),
+                            self.line_number,
+                            self.token_column,
+                        )
+                    })?,
+            }
+        };
+        self.push_operation(OperationType::Number, Some(object));
+
+        Ok(())
+    }
+
+    fn make_identifier(&mut self) -> Result<(), StuckError> {
+        let identifier = self.make_name();
+
+        match self.keywords.get(&identifier) {
+            Some(OperationType::Global) => {
+                self.make_declaration(OperationType::Global, "global")?
             }
+            Some(OperationType::For) => self.make_declaration(OperationType::For, "for")?,
+            Some(keyword) => self.push_operation(keyword.clone(), None),
+            None => self.push_operation(
+                OperationType::Identifier,
+                Some(Object::Identifier(identifier)),
+            ),
         }
+
+        Ok(())
     }
 
-    fn make_identifier(&mut self) {
-        let mut identifier = String::new();
+    fn make_name(&mut self) -> String {
+        let mut name = String::new();
         while let Some(current_charecter) = self.current_charecter {
             if current_charecter.is_alphanumeric() || current_charecter == '_' {
-                identifier.push(current_charecter);
+                name.push(current_charecter);
                 self.advance();
             } else {
-                if let Some(keyword) = self.keywords.get(&identifier) {
-                    let keyword = keyword;
-                    self.operations
-                        .push(Operation::new(keyword.clone(), None, self.line_number));
-                    return;
-                } else {
-                    self.operations.push(Operation::new(
-                        OperationType::Identifier,
-                        Some(Object::Identifier(identifier)),
-                        self.line_number,
-                    ));
-                    return;
-                }
+                break;
             }
         }
+        name
     }
 
-    fn make_string(&mut self) {
+    /// Keywords like `global` and `for` that are followed by the name of the variable they declare.
+    fn make_declaration(
+        &mut self,
+        op_type: OperationType,
+        keyword: &str,
+    ) -> Result<(), StuckError> {
+        while let Some(' ' | '\t' | '\r') = self.current_charecter {
+            self.advance();
+        }
+        let name = match self.current_charecter {
+            Some(current_charecter) if current_charecter.is_alphabetic() => self.make_name(),
+            _ => String::new(),
+        };
+        if name.is_empty() || self.keywords.contains_key(&name) {
+            return Err(self.error(
+                &format!("`{}` must be followed by a variable name", keyword),
+                self.line_number,
+                self.token_column,
+            ));
+        }
+
+        self.push_operation(op_type, Some(Object::Identifier(name)));
+
+        Ok(())
+    }
+
+    /// Scans a string literal, whose opening `"` is already consumed. `"""` strings can span
+    /// lines and raw strings (`r"..."`) keep backslashes and braces as they are.
+    fn make_string(&mut self, raw: bool) -> Result<(), StuckError> {
+        let line = self.line_number;
+        let triple = self.current_charecter == Some('"') && self.peek() == Some('"');
+        if triple {
+            self.advance();
+            self.advance();
+        }
+
         let mut string = String::new();
         while let Some(current_charecter) = self.current_charecter {
-            string.push(current_charecter);
-            self.advance();
-            if current_charecter == '"' {
-                let string = string
-                    .strip_suffix("\"")
-                    .unwrap()
-                    .replace("\\n", "\n")
-                    .replace("\\t", "\t");
-                self.operations.push(Operation::new(
-                    OperationType::String,
-                    Some(Object::String(string.to_string())),
+            match current_charecter {
+                '"' if !triple || self.source[self.index..].starts_with("\"\"") => {
+                    self.advance();
+                    if triple {
+                        self.advance();
+                        self.advance();
+                    }
+
+                    // The string belongs to the line it starts on.
+                    let end = std::mem::replace(&mut self.line_number, line);
+                    let pushed = if raw {
+                        self.push_operation(OperationType::String, Some(Object::String(string)));
+                        Ok(())
+                    } else {
+                        self.push_string(string)
+                    };
+                    self.line_number = end;
+                    return pushed;
+                }
+                '\\' if !raw => {
+                    let column = self.column;
+                    self.advance();
+                    string.push(self.make_escape(column)?);
+                }
+                '\n' if !triple => break,
+                '\n' => {
+                    string.push('\n');
+                    self.advance();
+                    self.line_number += 1;
+                }
+                _ => {
+                    string.push(current_charecter);
+                    self.advance();
+                }
+            }
+        }
+        self.unfinished = triple;
+        Err(self.error("unterminated string", line, self.token_column))
+    }
+
+    /// Scans what follows a `\` in a string. `column` is where the `\` is.
+    fn make_escape(&mut self, column: usize) -> Result<char, StuckError> {
+        let escape = match self.current_charecter {
+            Some(escape) if escape != '\n' => escape,
+            _ => {
+                return Err(self.error(
+                    "`\\` must be followed by an escape",
                     self.line_number,
-                ));
-                return;
-            } else if current_charecter == '\n' {
+                    column,
+                ))
+            }
+        };
+        self.advance();
+
+        let character = match escape {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '"' => '"',
+            '\\' => '\\',
+            'x' => {
+                let digits = self.make_hex_digits(2);
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(code) if digits.len() == 2 && code <= 0x7f => char::from(code),
+                    Ok(_) if digits.len() == 2 => {
+                        return Err(self.error(
+                            &format!(
+                                "`\\x{}` is not an ascii character (use `\\u{{...}}`)",
+                                digits
+                            ),
+                            self.line_number,
+                            column,
+                        ))
+                    }
+                    _ => {
+                        return Err(self.error(
+                            "`\\x` must be followed by two hex digits",
+                            self.line_number,
+                            column,
+                        ))
+                    }
+                }
+            }
+            'u' => {
+                let digits = match self.current_charecter {
+                    Some('{') => {
+                        self.advance();
+                        self.make_hex_digits(6)
+                    }
+                    _ => String::new(),
+                };
+                if digits.is_empty() || self.current_charecter != Some('}') {
+                    return Err(self.error(
+                        "`\\u` must be followed by one to six hex digits in braces, like `\\u{e9}`",
+                        self.line_number,
+                        column,
+                    ));
+                }
+                self.advance();
+                match u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                {
+                    Some(character) => character,
+                    None => {
+                        return Err(self.error(
+                            &format!("`\\u{{{}}}` is not a unicode character", digits),
+                            self.line_number,
+                            column,
+                        ))
+                    }
+                }
+            }
+            escape => {
+                return Err(self.error(
+                    &format!("unknown escape `\\{}`", escape),
+                    self.line_number,
+                    column,
+                ))
+            }
+        };
+
+        Ok(character)
+    }
+
+    /// Scans up to `limit` hex digits.
+    fn make_hex_digits(&mut self, limit: usize) -> String {
+        let mut digits = String::new();
+        while let Some(current_charecter) = self.current_charecter {
+            if digits.len() == limit || !current_charecter.is_ascii_hexdigit() {
                 break;
             }
+            digits.push(current_charecter);
+            self.advance();
         }
-        self.error("untermated string");
+        digits
     }
 
-    fn make_fuction(&mut self) {
-        while let Some(current_charecter) = self.current_charecter {
-            if current_charecter == ']' {
-                self.operations.push(Operation::new(
-                    OperationType::Function,
-                    None,
+    /// Pushes a string literal. If it has `{name}` placeholders, the variables are pushed
+    /// before it (with the names taken out of the placeholders) and it's `format`ted.
+    ///
+    /// Only names of variables make placeholders: braces around anything else (a keyword,
+    /// say) stay in the string as they are. Functions aren't called, interpolating one fails.
+    fn push_string(&mut self, string: String) -> Result<(), StuckError> {
+        let mut template = String::new();
+        let mut names = Vec::new();
+        let mut rest = string.as_str();
+
+        while let Some(brace) = rest.find(['{', '}']) {
+            template.push_str(&rest[..brace]);
+            rest = &rest[brace..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                template.push_str(&rest[..2]);
+                rest = &rest[2..];
+                continue;
+            }
+            if rest.starts_with('}') {
+                template.push_str("}}");
+                rest = &rest[1..];
+                continue;
+            }
+
+            let name: String = rest[1..]
+                .chars()
+                .take_while(|character| character.is_alphanumeric() || *character == '_')
+                .collect();
+            let spec = &rest[1 + name.len()..];
+            let end = spec.find('}').filter(|end| !spec[..*end].contains('{'));
+            match end {
+                Some(end)
+                    if name.starts_with(char::is_alphabetic)
+                        && !self.keywords.contains_key(&name)
+                        && (end == 0 || spec.starts_with(':')) =>
+                {
+                    template.push('{');
+                    template.push_str(&spec[..=end]);
+                    rest = &spec[end + 1..];
+                    names.push(name);
+                }
+                _ => {
+                    template.push_str("{{");
+                    rest = &rest[1..];
+                }
+            }
+        }
+        template.push_str(rest);
+
+        if names.is_empty() {
+            self.push_operation(OperationType::String, Some(Object::String(string)));
+            return Ok(());
+        }
+
+        if let Err(message) = template::parse(&template) {
+            return Err(self.error(
+                &format!("can not interpolate this string: {}", message),
+                self.line_number,
+                self.token_column,
+            ));
+        }
+        for name in names {
+            self.push_operation(OperationType::Interpolate, Some(Object::Identifier(name)));
+        }
+        self.push_operation(OperationType::String, Some(Object::String(template)));
+        self.push_operation(OperationType::Format, None);
+        Ok(())
+    }
+
+    /// Scans the signature of a function whose `[` is already consumed, if it has one. It
+    /// can start on a line of its own, but has to end on the line it starts on.
+    fn make_signature(&mut self) -> Result<(), StuckError> {
+        let checkpoint = (
+            self.index,
+            self.offset,
+            self.current_charecter,
+            self.column,
+            self.line_number,
+        );
+        while let Some(' ' | '\t' | '\r' | '\n') = self.current_charecter {
+            if self.current_charecter == Some('\n') {
+                self.line_number += 1;
+            }
+            self.advance();
+        }
+        let (signature_column, signature_start) = (self.column, self.offset);
+        let mut parameters: Vec<String> = Vec::new();
+        let mut results = 0;
+        let mut separated = false;
+
+        loop {
+            while let Some(' ' | '\t' | '\r') = self.current_charecter {
+                self.advance();
+            }
+            match self.current_charecter {
+                Some('-') if !separated && self.peek() == Some('-') => {
+                    self.advance();
+                    self.advance();
+                    separated = true;
+                }
+
+                Some('|') if separated => {
+                    self.advance();
+                    break;
+                }
+
+                Some(current_charecter) if current_charecter.is_alphabetic() => {
+                    let name = self.make_name();
+                    if self.keywords.contains_key(&name) {
+                        (
+                            self.index,
+                            self.offset,
+                            self.current_charecter,
+                            self.column,
+                            self.line_number,
+                        ) = checkpoint;
+                        return Ok(());
+                    }
+                    if separated {
+                        results += 1;
+                    } else {
+                        parameters.push(name);
+                    }
+                }
+
+                _ => {
+                    (
+                        self.index,
+                        self.offset,
+                        self.current_charecter,
+                        self.column,
+                        self.line_number,
+                    ) = checkpoint;
+                    return Ok(());
+                }
+            }
+        }
+
+        for (index, parameter) in parameters.iter().enumerate() {
+            if parameters[..index].contains(parameter) {
+                return Err(self.error(
+                    &format!("parameter `{}` is declared twice", parameter),
                     self.line_number,
+                    signature_column,
                 ));
-                self.advance();
-                return;
-            } else if current_charecter == '\n' {
-                self.advance();
-                self.line_number += 1;
-            } else {
-                self.scan();
-                return;
             }
         }
-        self.error("untermated function");
+        (self.token_column, self.token_start) = (signature_column, signature_start);
+        self.push_operation(
+            OperationType::Signature,
+            Some(Object::Signature(Signature {
+                parameters,
+                results,
+            })),
+        );
+
+        Ok(())
+    }
+
+    fn push_operation(&mut self, op_type: OperationType, operand: Option<Object>) {
+        let span = Span {
+            file: self.file,
+            start: self.token_start,
+            end: self.offset,
+            line: self.line_number,
+            column: self.token_column,
+        };
+        self.operations.push(Operation::new(op_type, operand, span));
+    }
+
+    /// Marks the current character as the start of the next operation.
+    fn start_token(&mut self) {
+        self.token_column = self.column;
+        self.token_start = self.offset;
     }
 
     fn init_keywords(&mut self) {
@@ -449,12 +870,91 @@ impl Tokenizer {
         self.keywords
             .insert("false".to_string(), OperationType::False);
 
+        self.keywords
+            .insert("global".to_string(), OperationType::Global);
+
         self.keywords
             .insert("include".to_string(), OperationType::Include);
         self.keywords
             .insert("read".to_string(), OperationType::Read);
         self.keywords
             .insert("write".to_string(), OperationType::Write);
+        self.keywords
+            .insert("writeln".to_string(), OperationType::WriteLine);
+        self.keywords
+            .insert("ewrite".to_string(), OperationType::EWrite);
+        self.keywords
+            .insert("flush".to_string(), OperationType::Flush);
+
+        self.keywords.insert("dup".to_string(), OperationType::Dup);
+        self.keywords
+            .insert("drop".to_string(), OperationType::Drop);
+        self.keywords
+            .insert("swap".to_string(), OperationType::Swap);
+        self.keywords
+            .insert("over".to_string(), OperationType::Over);
+        self.keywords.insert("rot".to_string(), OperationType::Rot);
+        self.keywords.insert("nip".to_string(), OperationType::Nip);
+        self.keywords
+            .insert("tuck".to_string(), OperationType::Tuck);
+        self.keywords
+            .insert("pick".to_string(), OperationType::Pick);
+        self.keywords
+            .insert("depth".to_string(), OperationType::Depth);
+
+        self.keywords.insert("int".to_string(), OperationType::Int);
+        self.keywords
+            .insert("float".to_string(), OperationType::Float);
+
+        self.keywords.insert("len".to_string(), OperationType::Len);
+        self.keywords.insert("get".to_string(), OperationType::Get);
+        self.keywords.insert("set".to_string(), OperationType::Set);
+        self.keywords
+            .insert("push".to_string(), OperationType::Push);
+        self.keywords.insert("pop".to_string(), OperationType::Pop);
+        self.keywords
+            .insert("slice".to_string(), OperationType::Slice);
+        self.keywords
+            .insert("concat".to_string(), OperationType::Concat);
+
+        self.keywords.insert("map".to_string(), OperationType::Map);
+        self.keywords.insert("put".to_string(), OperationType::Put);
+        self.keywords.insert("has".to_string(), OperationType::Has);
+        self.keywords.insert("del".to_string(), OperationType::Del);
+        self.keywords
+            .insert("keys".to_string(), OperationType::Keys);
+        self.keywords
+            .insert("values".to_string(), OperationType::Values);
+
+        self.keywords
+            .insert("format".to_string(), OperationType::Format);
+
+        self.keywords
+            .insert("substr".to_string(), OperationType::Substr);
+        self.keywords
+            .insert("index".to_string(), OperationType::Index);
+        self.keywords
+            .insert("split".to_string(), OperationType::Split);
+        self.keywords
+            .insert("join".to_string(), OperationType::Join);
+        self.keywords
+            .insert("upper".to_string(), OperationType::Upper);
+        self.keywords
+            .insert("lower".to_string(), OperationType::Lower);
+        self.keywords
+            .insert("trim".to_string(), OperationType::Trim);
+        self.keywords
+            .insert("replace".to_string(), OperationType::Replace);
+        self.keywords
+            .insert("startswith".to_string(), OperationType::StartsWith);
+        self.keywords
+            .insert("endswith".to_string(), OperationType::EndsWith);
+        self.keywords
+            .insert("chars".to_string(), OperationType::Chars);
+        self.keywords
+            .insert("tostr".to_string(), OperationType::ToStr);
+        self.keywords
+            .insert("tonum".to_string(), OperationType::ToNum);
 
         self.keywords.insert("if".to_string(), OperationType::If);
         self.keywords
@@ -464,6 +964,9 @@ impl Tokenizer {
 
         self.keywords
             .insert("while".to_string(), OperationType::While);
+        self.keywords.insert("for".to_string(), OperationType::For);
+        self.keywords
+            .insert("each".to_string(), OperationType::Each);
         self.keywords.insert("do".to_string(), OperationType::Do);
 
         self.keywords.insert("end".to_string(), OperationType::End);
@@ -471,8 +974,227 @@ impl Tokenizer {
 }
 
 impl Tokenizer {
-    fn error(&self, message: &str) {
-        eprintln!("Lexical Error: {} in line {}", message, self.line_number);
-        exit(1);
+    fn error(&self, message: &str, line: usize, column: usize) -> StuckError {
+        StuckError::Lexical(
+            Location::new(&self.file_name, line, column).pointing_into(&self.source, 1),
+            message.to_string(),
+        )
+    }
+
+    /// An error about an operation that's already scanned, underlining all of it.
+    fn error_at(&self, message: &str, span: Span) -> StuckError {
+        StuckError::Lexical(
+            Location::new(&self.file_name, span.line, span.column)
+                .pointing_into(&self.source, span.width(&self.source)),
+            message.to_string(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(source: &str) -> Vec<Operation> {
+        Tokenizer::new(source, 0, "test").scan_tokens().unwrap()
+    }
+
+    fn op_types(source: &str) -> Vec<OperationType> {
+        scan(source)
+            .into_iter()
+            .map(|operation| operation.op_type)
+            .collect()
+    }
+
+    #[test]
+    fn function_body_can_start_with_a_string() {
+        let source = "[ \"a.b\" writeln ]\n";
+        assert_eq!(
+            op_types(source),
+            vec![
+                OperationType::Function,
+                OperationType::String,
+                OperationType::WriteLine,
+                OperationType::Function,
+                OperationType::EndOfFile,
+            ]
+        );
+        assert_eq!(
+            scan(source)[1].operand,
+            Some(Object::String("a.b".to_string()))
+        );
+    }
+
+    #[test]
+    fn function_body_can_start_with_a_number() {
+        let operations = scan("[ 12 drop ]\n");
+        assert_eq!(operations[1].operand, Some(Object::Integer(12)));
+        assert_eq!(operations[2].op_type, OperationType::Drop);
+    }
+
+    #[test]
+    fn function_body_can_start_right_after_the_bracket() {
+        assert_eq!(
+            op_types("[dup]\n"),
+            vec![
+                OperationType::Function,
+                OperationType::Dup,
+                OperationType::Function,
+                OperationType::EndOfFile,
+            ]
+        );
+    }
+
+    #[test]
+    fn function_body_can_start_on_the_next_line() {
+        let operations = scan("[\n\"x\" write ]\n");
+        assert_eq!(operations[1].operand, Some(Object::String("x".to_string())));
+        assert_eq!(operations[1].span.line, 2);
+        assert_eq!(operations[1].span.column, 1);
+    }
+
+    #[test]
+    fn signature_is_read_before_the_body() {
+        let operations = scan("[ a b -- c | a b + ]\n");
+        assert_eq!(
+            operations[1].operand,
+            Some(Object::Signature(Signature {
+                parameters: vec!["a".to_string(), "b".to_string()],
+                results: 1,
+            }))
+        );
+        assert_eq!(operations[2].op_type, OperationType::Identifier);
+    }
+
+    #[test]
+    fn signature_can_be_on_the_line_after_the_bracket() {
+        let operations = scan("[\n  a b -- c |\n  a b +\n]\n");
+        assert_eq!(operations[1].op_type, OperationType::Signature);
+        assert_eq!((operations[1].span.line, operations[1].span.column), (2, 3));
+        assert_eq!(operations[2].span.line, 3);
+    }
+
+    #[test]
+    fn names_without_a_separator_are_not_a_signature() {
+        assert_eq!(
+            op_types("[\n  x y\n]\n"),
+            vec![
+                OperationType::Function,
+                OperationType::Identifier,
+                OperationType::Identifier,
+                OperationType::Function,
+                OperationType::EndOfFile,
+            ]
+        );
+    }
+
+    #[test]
+    fn placeholders_push_their_variables_before_the_template() {
+        let operations = scan("\"{a} and {b:>3}\"\n");
+        assert_eq!(
+            op_types("\"{a} and {b:>3}\"\n"),
+            vec![
+                OperationType::Interpolate,
+                OperationType::Interpolate,
+                OperationType::String,
+                OperationType::Format,
+                OperationType::EndOfFile,
+            ]
+        );
+        assert_eq!(
+            operations[2].operand,
+            Some(Object::String("{} and {:>3}".to_string()))
+        );
+    }
+
+    #[test]
+    fn keywords_in_braces_stay_text() {
+        let operations = scan("\"{end}\"\n");
+        assert_eq!(
+            operations[0].operand,
+            Some(Object::String("{end}".to_string()))
+        );
+
+        // Next to a placeholder, the braces are escaped so `format` keeps them.
+        let operations = scan("\"{end} {x} }\"\n");
+        assert_eq!(
+            operations[0].operand,
+            Some(Object::Identifier("x".to_string()))
+        );
+        assert_eq!(
+            operations[1].operand,
+            Some(Object::String("{{end}} {} }}".to_string()))
+        );
+    }
+
+    #[test]
+    fn bad_placeholder_spec_is_a_lexical_error() {
+        let error = Tokenizer::new("\"{x:abc}\"\n", 0, "test")
+            .scan_tokens()
+            .unwrap_err();
+        assert!(matches!(error, StuckError::Lexical(..)));
+    }
+
+    #[test]
+    fn escapes_are_replaced_except_in_raw_strings() {
+        let string = |source: &str| scan(source)[0].operand.clone();
+        assert_eq!(
+            string("\"a\\tb\\x41\\u{e9}\\\"\"\n"),
+            Some(Object::String("a\tbA\u{e9}\"".to_string()))
+        );
+        assert_eq!(
+            string("r\"a\\n{b}\"\n"),
+            Some(Object::String("a\\n{b}".to_string()))
+        );
+        assert_eq!(
+            string("\"\"\"one \"two\"\nthree\"\"\"\n"),
+            Some(Object::String("one \"two\"\nthree".to_string()))
+        );
+    }
+
+    #[test]
+    fn bad_escapes_are_errors() {
+        for source in [
+            "\"\\q\"\n",
+            "\"\\x4\"\n",
+            "\"\\xff\"\n",
+            "\"\\u{110000}\"\n",
+        ] {
+            let error = Tokenizer::new(source, 0, "test").scan_tokens().unwrap_err();
+            assert!(matches!(error, StuckError::Lexical(..)), "`{}`", source);
+        }
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        let operations = scan("\"héllo wörld\" x\n");
+        assert_eq!(
+            operations[0].operand,
+            Some(Object::String("héllo wörld".to_string()))
+        );
+        assert_eq!(operations[1].span.column, 15);
+    }
+
+    #[test]
+    fn unclosed_function_is_unfinished() {
+        assert!(Tokenizer::is_unfinished("[ 1\n"));
+        assert!(!Tokenizer::is_unfinished("[ 1 ]\n"));
+        assert!(Tokenizer::is_unfinished("{ 1\n"));
+        assert!(Tokenizer::is_unfinished("\"\"\"a\n"));
+        assert!(!Tokenizer::is_unfinished("1 end\n"));
+    }
+
+    #[test]
+    fn blocks_without_their_opening_word_are_errors() {
+        for (source, message) in [
+            ("1 then 2 else 3 end\n", "unexpected `then`"),
+            ("1 then 2 end\n", "unexpected `then`"),
+            ("else 3 end\n", "invalid `else`"),
+            ("1 end\n", "unexpected `end`"),
+        ] {
+            let error = Tokenizer::new(source, 0, "test").scan_tokens().unwrap_err();
+            assert!(matches!(error, StuckError::Lexical(..)), "{:?}", error);
+            assert_eq!(error.message(), message, "`{}`", source.trim());
+        }
     }
 }
@@ -0,0 +1,193 @@
+//! Runs the `stuck` binary the way it's used from a shell.
+
+use std::{
+    env, fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+fn stuck(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_stuck"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+/// `13_recursion.stk` nests ten calls deep.
+const RECURSION: &str = "examples/syntax/13_recursion.stk";
+
+#[test]
+fn max_call_depth_limits_recursion() {
+    let output = stuck(&[RECURSION, "--max-call-depth", "20"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3628800\n");
+
+    // The option can come before the file, too.
+    let output = stuck(&["--max-call-depth", "5", RECURSION]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr(&output).contains("StackOverflow"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn max_call_depth_requires_a_whole_number() {
+    for args in [
+        &[RECURSION, "--max-call-depth"][..],
+        &[RECURSION, "--max-call-depth", "ten"],
+        &[RECURSION, "--max-call-depth", "-1"],
+    ] {
+        let output = stuck(args);
+        assert_eq!(output.status.code(), Some(1), "{:?}", args);
+        assert!(
+            stderr(&output).contains("`--max-call-depth` requires a whole number"),
+            "{:?}: {}",
+            args,
+            stderr(&output)
+        );
+    }
+}
+
+/// Runs the REPL with `input` piped in, in a home directory of its own (it keeps its history
+/// there), and returns its output and that directory.
+fn repl(name: &str, input: &str) -> (Output, PathBuf) {
+    let home = env::temp_dir().join(format!("stuck-repl-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&home).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_stuck"))
+        .env("HOME", &home)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    (child.wait_with_output().unwrap(), home)
+}
+
+#[test]
+fn repl_waits_for_blocks_to_be_closed_and_keeps_going_after_errors() {
+    let (output, home) = repl(
+        "blocks",
+        "[ n -- m |\n  n 2 *\n] @double 21 double writeln\nnope\n1 writeln\n",
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n1\n");
+    assert!(
+        stderr(&output).contains("UndefinedVariable"),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(
+        fs::read_to_string(home.join(".stuck_history"))
+            .unwrap()
+            .lines()
+            .count(),
+        5
+    );
+
+    fs::remove_dir_all(home).unwrap();
+}
+
+#[test]
+fn repl_commands_show_the_stack_and_variables() {
+    let (output, home) = repl(
+        "commands",
+        "1 @x \"two\" 3\n:stack\n:vars\n:clear\n:stack\n:nope\n",
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "<2> \"two\" 3\nx = 1\n<0>\n"
+    );
+    assert!(
+        stderr(&output).contains("unknown command `:nope`"),
+        "{}",
+        stderr(&output)
+    );
+
+    fs::remove_dir_all(home).unwrap();
+}
+
+#[test]
+fn repl_sessions_can_be_saved_and_restored() {
+    let session = env::temp_dir().join(format!("stuck-session-{}.stk", std::process::id()));
+    let session = session.display();
+    let (output, home) = repl(
+        "save",
+        &format!(
+            "[ n -- m | n 2 * ] @double \"hi\" @greeting\n:save {}\n:reset\n:restore {}\n21 double writeln greeting writeln\n",
+            session, session
+        ),
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "42\nhi\n");
+
+    fs::remove_dir_all(home).unwrap();
+    fs::remove_file(session.to_string()).unwrap();
+}
+
+#[test]
+fn repl_help_describes_words_and_variables() {
+    let (output, home) = repl(
+        "help",
+        "[ n -- m | n 2 * ] @double 1 @one\n:help dup\n:help double\n:help one\n:help nope\n",
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "dup  ( a -- a a )  copies the top of the stack\ndouble  [ n -- m | n 2 * ]\none = 1\n"
+    );
+    assert!(
+        stderr(&output).contains("`nope` is not a builtin or a variable"),
+        "{}",
+        stderr(&output)
+    );
+
+    fs::remove_dir_all(home).unwrap();
+}
+
+#[test]
+fn debugger_stops_at_breakpoints_and_shows_variables() {
+    let directory = env::temp_dir().join(format!("stuck-debug-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let source = directory.join("main.stk");
+    fs::write(&source, "1 @x\n2 @x\nx writeln\n").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_stuck"))
+        .arg("debug")
+        .arg(&source)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"break 3\ncontinue\nprint x\ncontinue\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("3 | x writeln"), "{}", stdout);
+    assert!(stdout.ends_with("x = 2\n2\n"), "{}", stdout);
+
+    fs::remove_dir_all(directory).unwrap();
+}
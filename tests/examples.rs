@@ -0,0 +1,199 @@
+//! Runs every example and compares what it writes with `tests/examples/<example>.out`
+//! (fed from `tests/examples/<example>.in`, if there is one).
+
+use std::{
+    env, fs,
+    io::Write,
+    path::Path,
+    process::{Command, Output, Stdio},
+};
+
+/// Examples that use what the compiler doesn't support yet (big integers, lists, maps and string
+/// words), so they're only interpreted.
+const NOT_COMPILED: &[&str] = &[
+    "io",
+    "syntax/14_numbers",
+    "syntax/15_list",
+    "syntax/16_map",
+    "syntax/17_string",
+    "syntax/18_format",
+];
+
+/// The examples, as paths from `examples/` without the extension.
+fn examples() -> Vec<String> {
+    let mut examples = Vec::new();
+    for directory in ["", "syntax"] {
+        for entry in fs::read_dir(Path::new("examples").join(directory)).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|extension| extension == "stk") {
+                let name = path.file_stem().unwrap().to_string_lossy();
+                examples.push(match directory {
+                    "" => name.to_string(),
+                    _ => format!("{}/{}", directory, name),
+                });
+            }
+        }
+    }
+    examples.sort();
+    examples
+}
+
+fn input(example: &str) -> Vec<u8> {
+    fs::read(format!("tests/examples/{}.in", example)).unwrap_or_default()
+}
+
+/// Runs `command` with `input` on stdin, and returns what it wrote to stdout.
+fn output(mut command: Command, input: &[u8]) -> Result<String, String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| err.to_string())?;
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let Output {
+        status,
+        stdout,
+        stderr,
+    } = child.wait_with_output().unwrap();
+    if !status.success() {
+        return Err(String::from_utf8_lossy(&stderr).to_string());
+    }
+    Ok(String::from_utf8_lossy(&stdout).to_string())
+}
+
+fn interpret(source: &Path, input: &[u8]) -> Result<String, String> {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_stuck"));
+    command.arg(source);
+    output(command, input)
+}
+
+/// Compiles `source` and runs the executable it's compiled to.
+fn compile(source: &Path, input: &[u8]) -> Result<String, String> {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_stuck"));
+    command.arg(source).arg("-c");
+    output(command, &[])?;
+    output(Command::new(source.with_extension("")), input)
+}
+
+/// Whether there's a C compiler to compile with (tests that need one are skipped otherwise).
+fn can_compile() -> bool {
+    let c_compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let found = Command::new(&c_compiler).arg("--version").output().is_ok();
+    if !found {
+        eprintln!("skipped: there's no C compiler (`{}`)", c_compiler);
+    }
+    found
+}
+
+fn expected(example: &str) -> String {
+    fs::read_to_string(format!("tests/examples/{}.out", example))
+        .unwrap_or_else(|err| panic!("`{}` has no expected output ({})", example, err))
+}
+
+/// Checks every example with `run`, and fails with all of the examples that went wrong.
+fn check(examples: &[String], run: impl Fn(&str) -> Result<String, String>) {
+    let mut failures = Vec::new();
+    for example in examples {
+        match run(example) {
+            Ok(output) if output == expected(example) => {}
+            Ok(output) => failures.push(format!(
+                "`{}` wrote:\n{}\ninstead of:\n{}",
+                example,
+                output,
+                expected(example)
+            )),
+            Err(err) => failures.push(format!("`{}` failed:\n{}", example, err)),
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn examples_write_what_they_should() {
+    check(&examples(), |example| {
+        interpret(
+            Path::new(&format!("examples/{}.stk", example)),
+            &input(example),
+        )
+    });
+}
+
+#[test]
+fn compiled_examples_write_the_same() {
+    if !can_compile() {
+        return;
+    }
+
+    // The compiler writes next to the source, so compile copies of the examples.
+    let directory = env::temp_dir().join(format!("stuck-examples-{}", std::process::id()));
+    copy(Path::new("examples"), &directory);
+
+    let examples: Vec<String> = examples()
+        .into_iter()
+        .filter(|example| !NOT_COMPILED.contains(&example.as_str()))
+        .collect();
+    check(&examples, |example| {
+        compile(&directory.join(format!("{}.stk", example)), &input(example))
+    });
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+fn copy(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let path = entry.unwrap().path();
+        let destination = to.join(path.file_name().unwrap());
+        if path.is_dir() {
+            copy(&path, &destination);
+        } else {
+            fs::copy(&path, &destination).unwrap();
+        }
+    }
+}
+
+#[test]
+fn files_run_once_however_they_are_included() {
+    let directory = env::temp_dir().join(format!("stuck-includes-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("lib.stk"), "\"lib ran, \" write\n").unwrap();
+    // The include that runs the file isn't the first one in the source.
+    let source = directory.join("main.stk");
+    fs::write(
+        &source,
+        "\
+if false then \"lib.stk\" include end
+[ \"lib.stk\" include ] @load
+load load
+\"done\" write
+",
+    )
+    .unwrap();
+
+    assert_eq!(interpret(&source, &[]).unwrap(), "lib ran, done");
+    if can_compile() {
+        assert_eq!(compile(&source, &[]).unwrap(), "lib ran, done");
+    }
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn include_cycles_are_errors_however_they_are_run() {
+    let directory = env::temp_dir().join(format!("stuck-cycle-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("a.stk"), "\"b.stk\" include\n").unwrap();
+    fs::write(directory.join("b.stk"), "\"a.stk\" include\n").unwrap();
+    let source = directory.join("main.stk");
+    fs::write(&source, "\"a.stk\" include\n").unwrap();
+
+    let error = interpret(&source, &[]).unwrap_err();
+    assert!(error.contains("include cycle"), "{}", error);
+    if can_compile() {
+        let error = compile(&source, &[]).unwrap_err();
+        assert!(error.contains("include cycle"), "{}", error);
+    }
+
+    fs::remove_dir_all(directory).unwrap();
+}
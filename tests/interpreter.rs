@@ -0,0 +1,487 @@
+use std::{cell::RefCell, rc::Rc};
+
+use stuck::{BigInt, Debugger, Interpreter, Object, StuckError};
+
+/// Runs `source` on a new interpreter and returns it, so its stack and variables can be checked.
+fn run(source: &str) -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    if let Err(err) = interpreter.run(source) {
+        panic!("`{}` failed: {}", source, err);
+    }
+    interpreter
+}
+
+fn fail(source: &str) -> StuckError {
+    match Interpreter::new().run(source) {
+        Ok(()) => panic!("`{}` should have failed", source),
+        Err(err) => err.untraced().clone(),
+    }
+}
+
+fn string(text: &str) -> Object {
+    Object::String(text.to_string())
+}
+
+#[test]
+fn arguments_and_results_go_through_the_stack() {
+    let mut interpreter = Interpreter::new();
+    interpreter.push(Object::Integer(2));
+    interpreter.push(Object::Integer(3));
+    interpreter.run("* 1 +\n").unwrap();
+    assert_eq!(interpreter.pop(), Some(Object::Integer(7)));
+    assert_eq!(interpreter.pop(), None);
+}
+
+#[test]
+fn files_included_by_a_function_declare_globals() {
+    let interpreter = run("[ \"examples/syntax/lib/greet.stk\" include 1 @local ] @load\nload\n");
+    assert!(matches!(
+        interpreter.get_variable("greet"),
+        Some(Object::Function(_))
+    ));
+    assert_eq!(interpreter.get_variable("separator"), Some(&string("!\n")));
+    assert_eq!(interpreter.get_variable("local"), None);
+}
+
+fn integer(source: &str, name: &str) -> i64 {
+    match run(source).get_variable(name) {
+        Some(Object::Integer(integer)) => *integer,
+        other => panic!("`{}` is {:?} after `{}`", name, other, source),
+    }
+}
+
+#[test]
+fn the_stack_and_variables_can_be_inspected_and_reset() {
+    let mut interpreter = run("2 @b 1 @a 3 4\n");
+    assert_eq!(
+        interpreter.stack(),
+        [Object::Integer(3), Object::Integer(4)]
+    );
+    let names: Vec<&str> = interpreter
+        .variables()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(names, ["a", "b"]);
+
+    interpreter.clear_stack();
+    assert!(interpreter.stack().is_empty());
+    assert_eq!(interpreter.get_variable("a"), Some(&Object::Integer(1)));
+
+    interpreter.set_max_call_depth(5);
+    interpreter.reset();
+    assert!(interpreter.variables().is_empty());
+    // Settings outlive the reset.
+    let error = interpreter
+        .run("[ forever ] @forever\nforever\n")
+        .unwrap_err();
+    assert!(error.message().contains("depth of 5"), "{}", error);
+}
+
+#[test]
+fn variables_can_be_reassigned_and_read_by_functions_declared_before_them() {
+    assert_eq!(integer("1 @x\nx 1 + @x\n", "x"), 2);
+    assert_eq!(integer("[ x 2 * ] @double\n21 @x\ndouble @y\n", "y"), 42);
+}
+
+#[test]
+fn undefined_variables_are_errors() {
+    assert!(matches!(
+        fail("nope writeln\n"),
+        StuckError::UndefinedVariable(..)
+    ));
+    assert!(matches!(
+        fail("[ 1 @local ] @f\nf local\n"),
+        StuckError::UndefinedVariable(..)
+    ));
+}
+
+#[test]
+fn locals_shadow_globals_only_during_the_call() {
+    let interpreter = run("1 @x\n[ 2 @x global seen x @seen ] @f\nf\n");
+    assert_eq!(interpreter.get_variable("x"), Some(&Object::Integer(1)));
+    assert_eq!(interpreter.get_variable("seen"), Some(&Object::Integer(2)));
+}
+
+#[test]
+fn global_assigns_outside_of_the_function() {
+    assert_eq!(integer("0 @n\n[ global n n 1 + @n ] @f\nf f f\n", "n"), 3);
+    // Declaring the global first isn't needed.
+    assert_eq!(integer("[ global n 5 @n ] @f\nf\n", "n"), 5);
+}
+
+#[test]
+fn every_call_has_its_own_parameters_and_locals() {
+    let source = "\
+[ n -- result |
+    n 1 - @smaller
+    if n 2 < then 1 else smaller factorial n * end
+    global last smaller @last
+] @factorial
+5 factorial @result
+";
+    assert_eq!(integer(source, "result"), 120);
+    // Every call assigned `last` after its inner calls returned, so the outermost call was last.
+    assert_eq!(integer(source, "last"), 4);
+}
+
+#[test]
+fn functions_can_call_functions_declared_after_them() {
+    assert_eq!(
+        integer("[ inc inc ] @twice\n[ 1 + ] @inc\n1 twice @y\n", "y"),
+        3
+    );
+}
+
+#[test]
+fn signatures_check_the_arguments_and_results() {
+    let interpreter = run("[ a b -- c | a b - ] @sub\n10 3 sub @x\n");
+    assert_eq!(interpreter.get_variable("x"), Some(&Object::Integer(7)));
+    assert!(matches!(
+        fail("[ a b -- c | a b - ] @sub\n1 sub\n"),
+        StuckError::StackUnderflow(..)
+    ));
+    assert!(matches!(
+        fail("[ a -- b c | a ] @f\n1 f\n"),
+        StuckError::ArityMismatch(..)
+    ));
+}
+
+#[test]
+fn errors_in_functions_are_traced_back_to_the_calls() {
+    let error = Interpreter::new()
+        .run("[ drop ] @inner\n[ inner ] @outer\nouter\n")
+        .unwrap_err();
+    assert!(matches!(error.untraced(), StuckError::StackUnderflow(..)));
+    let trace: Vec<(&str, usize)> = error
+        .trace()
+        .iter()
+        .map(|call| (call.function.as_str(), call.location.line))
+        .collect();
+    assert_eq!(trace, [("inner", 2), ("outer", 3)]);
+}
+
+#[test]
+fn calls_can_only_nest_so_deep() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_max_call_depth(50);
+    let error = interpreter
+        .run("[ forever ] @forever\nforever\n")
+        .unwrap_err();
+    assert!(matches!(error.untraced(), StuckError::StackOverflow(..)));
+
+    // The failed calls are gone, so the interpreter can go as deep as before.
+    interpreter
+        .run("[ n -- | if n 0 > then n 1 - count end ] @count\n49 count\n")
+        .unwrap();
+}
+
+#[test]
+fn errors_underline_where_they_are_in_their_line() {
+    let error = fail("1 @x\nx  nope +\n");
+    let location = error.location();
+    assert_eq!((location.line, location.column), (2, 4));
+    let snippet = location.snippet.as_ref().unwrap();
+    assert_eq!((snippet.text.as_str(), snippet.width), ("x  nope +", 4));
+    assert!(
+        error.to_string().ends_with("2 | x  nope +\n  |    ^^^^"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn if_runs_the_first_block_whose_condition_holds() {
+    let source = |x: &str| {
+        format!(
+            "{} @x\nif x 0 < then 0 1 - @sign else x 0 = then 0 @sign else 1 @sign end\n",
+            x
+        )
+    };
+    assert_eq!(integer(&source("0 5 -"), "sign"), -1);
+    assert_eq!(integer(&source("0"), "sign"), 0);
+    assert_eq!(integer(&source("5"), "sign"), 1);
+    assert_eq!(integer("0 @x\nif false then 1 @x end\n", "x"), 0);
+}
+
+#[test]
+fn nested_loops_jump_back_to_their_own_condition() {
+    let source = "\
+0 @sum 0 @i
+while i 3 < do
+    0 @j
+    while j 4 < do
+        if i j = then sum 1 + @sum end
+        j 1 + @j
+    end
+    i 1 + @i
+end
+";
+    let interpreter = run(source);
+    assert_eq!(interpreter.get_variable("sum"), Some(&Object::Integer(3)));
+    assert_eq!(interpreter.get_variable("i"), Some(&Object::Integer(3)));
+    assert_eq!(interpreter.get_variable("j"), Some(&Object::Integer(4)));
+}
+
+#[test]
+fn list_words_leave_a_changed_copy() {
+    let interpreter = run("{ 1 2 } @a\na 0 5 set @b\na 3 push @c\n");
+    let list =
+        |items: &[i64]| Object::List(items.iter().map(|&item| Object::Integer(item)).collect());
+    assert_eq!(interpreter.get_variable("a"), Some(&list(&[1, 2])));
+    assert_eq!(interpreter.get_variable("b"), Some(&list(&[5, 2])));
+    assert_eq!(interpreter.get_variable("c"), Some(&list(&[1, 2, 3])));
+    assert!(matches!(
+        fail("{ 1 2 } 2 get\n"),
+        StuckError::IndexOutOfRange(..)
+    ));
+}
+
+#[test]
+fn maps_are_keyed_by_value() {
+    let interpreter = run("map 1 \"one\" put @a\na 1.0 get @one\na 1 del @b\n");
+    assert_eq!(interpreter.get_variable("one"), Some(&string("one")));
+    assert_eq!(
+        interpreter.get_variable("b"),
+        Some(&Object::Map(Default::default()))
+    );
+    assert!(matches!(
+        fail("map \"nope\" get\n"),
+        StuckError::MissingKey(..)
+    ));
+}
+
+#[test]
+fn string_words_cut_and_join() {
+    let mut interpreter =
+        run("\"hello\" len \"hello\" 1 3 substr \"a,b\" \",\" split \"-\" join\n");
+    assert_eq!(interpreter.pop(), Some(string("a-b")));
+    assert_eq!(interpreter.pop(), Some(string("el")));
+    assert_eq!(interpreter.pop(), Some(Object::Integer(5)));
+}
+
+#[test]
+fn tonum_reads_only_finite_numbers() {
+    let mut interpreter = run("\" 42 \" tonum \"1.5\" tonum \"99999999999999999999\" tonum\n");
+    assert!(matches!(interpreter.pop(), Some(Object::BigInt(_))));
+    assert_eq!(interpreter.pop(), Some(Object::Number(1.5)));
+    assert_eq!(interpreter.pop(), Some(Object::Integer(42)));
+
+    for text in ["nan", "inf", "-infinity", "1e999"] {
+        assert!(
+            matches!(
+                fail(&format!("\"{}\" tonum\n", text)),
+                StuckError::InvalidValue(..)
+            ),
+            "`{}` was read as a number",
+            text
+        );
+    }
+}
+
+#[test]
+fn interpolation_fills_in_variables() {
+    let interpreter = run("\"stuck\" @name 7 @age\n\"{name} is {age:03}\" @text\n");
+    assert_eq!(
+        interpreter.get_variable("text"),
+        Some(&string("stuck is 007"))
+    );
+}
+
+#[test]
+fn interpolation_keeps_keywords_as_text() {
+    let interpreter = run("1 @x\n\"{end}\" @a \"{if} {x}\" @b\n");
+    assert_eq!(interpreter.get_variable("a"), Some(&string("{end}")));
+    assert_eq!(interpreter.get_variable("b"), Some(&string("{if} 1")));
+}
+
+#[test]
+fn interpolation_does_not_call_functions() {
+    let error = fail("0 @calls\n[ calls 1 + @calls ] @f\n\"{f}\" writeln\n");
+    assert!(matches!(error, StuckError::InvalidType(..)), "{:?}", error);
+}
+
+#[test]
+fn interpolation_of_an_undefined_variable_fails() {
+    assert!(matches!(
+        fail("\"{nope}\" writeln\n"),
+        StuckError::UndefinedVariable(..)
+    ));
+}
+
+#[test]
+fn huge_widths_are_errors_instead_of_allocations() {
+    assert!(matches!(
+        fail("5 \"{:99999999999}\" format\n"),
+        StuckError::InvalidValue(..)
+    ));
+    assert!(matches!(
+        fail("5 @x \"{x:.99999999}\" writeln\n"),
+        StuckError::Lexical(..)
+    ));
+}
+
+#[test]
+fn for_and_each_go_through_every_item() {
+    assert_eq!(
+        integer("0 @sum\n{ 1 2 3 } for x do sum x + @sum end\n", "sum"),
+        6
+    );
+    assert_eq!(
+        integer("0 @sum\n{ 1 2 3 } each do sum + @sum end\n", "sum"),
+        6
+    );
+    assert_eq!(
+        integer(
+            "0 @n\n{ } for x do n 1 + @n end\n{ } each do n 1 + @n end\n",
+            "n"
+        ),
+        0
+    );
+}
+
+#[test]
+fn loops_in_functions_use_the_locals_of_the_call() {
+    let source = "\
+[ n -- total |
+    0 @total
+    while n 0 > do
+        total n + @total
+        n 1 - @n
+    end
+    total
+] @sum_to
+10 sum_to @x
+";
+    let mut interpreter = run(source);
+    assert_eq!(interpreter.get_variable("x"), Some(&Object::Integer(55)));
+    assert_eq!(interpreter.get_variable("total"), None);
+    assert_eq!(interpreter.pop(), None);
+}
+
+#[test]
+fn integers_stay_integers_until_they_meet_a_float() {
+    let mut interpreter = run("7 2 / 7 2.0 / 3.9 int 7 float");
+    assert_eq!(interpreter.pop(), Some(Object::Number(7.0)));
+    assert_eq!(interpreter.pop(), Some(Object::Integer(3)));
+    assert_eq!(interpreter.pop(), Some(Object::Number(3.5)));
+    assert_eq!(interpreter.pop(), Some(Object::Integer(3)));
+
+    assert!(matches!(fail("1 0 /"), StuckError::DivisionByZero(..)));
+}
+
+#[test]
+fn integers_grow_when_they_overflow_and_shrink_back() {
+    let interpreter = run("9223372036854775807 1 + @big\nbig 1 - @back\n0 big - 1 + @negative\n");
+    assert_eq!(
+        interpreter.get_variable("big"),
+        Some(&Object::BigInt(
+            BigInt::from_decimal("9223372036854775808").unwrap()
+        ))
+    );
+    assert_eq!(
+        interpreter.get_variable("back"),
+        Some(&Object::Integer(i64::MAX))
+    );
+    assert_eq!(
+        interpreter.get_variable("negative"),
+        Some(&Object::Integer(i64::MIN + 1))
+    );
+}
+
+#[test]
+fn files_that_fail_can_be_included_again() {
+    let directory =
+        std::env::temp_dir().join(format!("stuck-include-again-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("lib.stk");
+    let include = format!("\"{}\" include\n", path.display());
+
+    let mut interpreter = Interpreter::new();
+    std::fs::write(&path, "drop 1 @x\n").unwrap();
+    assert!(interpreter.run(&include).is_err());
+    std::fs::write(&path, "1 @x\n").unwrap();
+    interpreter.run(&include).unwrap();
+    assert_eq!(interpreter.get_variable("x"), Some(&Object::Integer(1)));
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn runs_that_fail_change_neither_the_stack_nor_the_variables() {
+    let mut interpreter = run("1 @x 2");
+    assert!(interpreter.run("5 @x 3 @y 4 drop drop drop drop").is_err());
+    assert_eq!(interpreter.get_variable("x"), Some(&Object::Integer(1)));
+    assert_eq!(interpreter.get_variable("y"), None);
+    assert_eq!(interpreter.stack(), [Object::Integer(2)]);
+
+    // What ran before the failure is still there to build on.
+    interpreter.run("x + @x").unwrap();
+    assert_eq!(interpreter.get_variable("x"), Some(&Object::Integer(3)));
+}
+
+#[test]
+fn functions_declared_by_earlier_runs_can_be_called() {
+    let mut interpreter = run("[ n -- m | n 2 * ] @double\n");
+    interpreter.run("21 double @x\n").unwrap();
+    assert_eq!(interpreter.get_variable("x"), Some(&Object::Integer(42)));
+}
+
+/// Writes down what the program looks like before every operation on `line`, and stops it
+/// the second time it gets there.
+struct Recorder {
+    line: usize,
+    seen: Rc<RefCell<Vec<String>>>,
+}
+
+impl Debugger for Recorder {
+    fn before(&mut self, interpreter: &Interpreter, at: usize) -> bool {
+        if interpreter.location(at).line != self.line {
+            return true;
+        }
+        let mut seen = self.seen.borrow_mut();
+        let calls: Vec<String> = interpreter
+            .calls()
+            .iter()
+            .map(|call| call.function.clone())
+            .collect();
+        let locals: Vec<String> = interpreter
+            .locals()
+            .iter()
+            .map(|(name, object)| format!("{}={:?}", name, object))
+            .collect();
+        seen.push(format!(
+            "{} [{}] [{}] x={:?}",
+            interpreter.call_depth(),
+            calls.join(" "),
+            locals.join(" "),
+            interpreter.visible_variable("x").unwrap(),
+        ));
+        seen.len() < 2
+    }
+}
+
+#[test]
+fn debuggers_see_the_calls_and_locals_and_can_stop_the_program() {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let mut interpreter = Interpreter::new();
+    interpreter.set_debugger(Some(Box::new(Recorder {
+        line: 3,
+        seen: seen.clone(),
+    })));
+    interpreter
+        .run("1 @x\n[ n -- | global done 2 @x n\n  @done\n] @f\n5 f\n6 f\n7 @after\n")
+        .unwrap();
+
+    assert_eq!(
+        *seen.borrow(),
+        [
+            "1 [f] [n=Integer(5) x=Integer(2)] x=Integer(2)",
+            "1 [f] [n=Integer(6) x=Integer(2)] x=Integer(2)",
+        ]
+    );
+    // Stopping ends the run where it is, but keeps what it did so far.
+    assert_eq!(interpreter.get_variable("done"), Some(&Object::Integer(5)));
+    assert_eq!(interpreter.get_variable("after"), None);
+    assert_eq!(interpreter.get_variable("x"), Some(&Object::Integer(1)));
+}